@@ -0,0 +1,35 @@
+use crate::config::Config;
+use crate::es_client::EsClient;
+use crate::mappings;
+
+/// `indices`: prints, for every entity, the configured index or alias name
+/// and the concrete indices it currently resolves to.
+pub async fn show_indices(config: &Config) -> Result<(), String> {
+    let es = EsClient::from_config(&config.elasticsearch)
+        .map_err(|err| format!("Failed to create Elasticsearch client: {}", err))?;
+    let names = config.elasticsearch.index_names();
+
+    for (entity, name) in names.entries() {
+        let resolved = match es.resolve_index(name).await {
+            Ok(indices) if indices.is_empty() => "(missing)".to_string(),
+            Ok(indices) => indices.join(", "),
+            Err(err) => format!("(error: {})", err),
+        };
+        println!("{:<12}{:<32}-> {}", entity, name, resolved);
+    }
+
+    Ok(())
+}
+
+/// `mappings <entity>`: prints the settings and mappings a new index of
+/// `entity` should be created with.
+pub fn show_mappings(entity: Option<&str>) -> Result<(), String> {
+    let available = mappings::ENTITIES.join(", ");
+    let entity = entity.ok_or_else(|| format!("Usage: mappings <entity>, one of: {}", available))?;
+    let mappings = mappings::for_entity(entity)
+        .ok_or_else(|| format!("Unknown entity `{}`, expected one of: {}", entity, available))?;
+    let body = serde_json::to_string_pretty(&mappings).map_err(|err| err.to_string())?;
+    println!("{}", body);
+
+    Ok(())
+}
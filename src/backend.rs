@@ -0,0 +1,178 @@
+pub mod elasticsearch;
+pub mod memory;
+
+use hyper::StatusCode;
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use crate::config::SearchConfig;
+use crate::es_client::EsError;
+
+pub use elasticsearch::ElasticsearchBackend;
+pub use memory::MemoryBackend;
+
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, BackendError>> + Send + 'a>>;
+
+/// Filters and paging for a story search. `None`/`false` fields do not
+/// restrict the result.
+#[derive(Debug, Clone, Default)]
+pub struct StoryQuery {
+    // Free text matched against titles, alternate titles, author names and
+    // descriptions, with or without diacritics
+    pub text: Option<String>,
+    pub author_id: Option<String>,
+    pub category_id: Option<String>,
+    pub is_full: bool,
+    pub sort_by_latest: bool,
+    // From 1; 0 is read as 1
+    pub page: usize,
+    pub size: usize,
+    // Look for spelling corrections of `text` when it finds few stories
+    pub did_you_mean: Option<DidYouMean>,
+    // Mark where `text` matched, in a `_highlight` object added to each story
+    pub highlight: Option<Highlight>,
+}
+
+/// When a text search is sparse enough to be worth correcting, and how
+/// many corrections to look for then.
+#[derive(Debug, Clone, Copy)]
+pub struct DidYouMean {
+    // Fewer matching stories than this
+    pub below: u64,
+    pub size: usize,
+}
+
+impl DidYouMean {
+    /// `None` when the config turns corrections off.
+    pub fn new(config: &SearchConfig) -> Option<Self> {
+        (config.did_you_mean_size > 0 && config.did_you_mean_below > 0)
+            .then_some(DidYouMean { below: config.did_you_mean_below, size: config.did_you_mean_size })
+    }
+
+    pub fn is_sparse(&self, total: u64) -> bool {
+        total < self.below
+    }
+}
+
+/// Markup for highlighted search results. Titles are highlighted whole;
+/// descriptions as fragments of about `fragment_size` characters around
+/// the matches.
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    pub pre_tag: String,
+    pub post_tag: String,
+    pub fragment_size: usize,
+}
+
+impl Highlight {
+    pub fn new(config: &SearchConfig) -> Self {
+        Highlight {
+            pre_tag: config.highlight_pre_tag.clone(),
+            post_tag: config.highlight_post_tag.clone(),
+            fragment_size: config.highlight_fragment_size,
+        }
+    }
+}
+
+/// One page of documents plus the total number of matches.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub items: Vec<Value>,
+    pub total: u64,
+    // Corrected spellings of the query text that do match stories, best
+    // first; `None` unless a story search asked for them and came up sparse
+    pub suggestions: Option<Vec<String>>,
+}
+
+impl Page {
+    pub fn total_pages(&self, size: usize) -> usize {
+        (self.total as f64 / size as f64).ceil() as usize
+    }
+}
+
+/// Completions for what a reader is typing: documents whose title starts
+/// like the query, up to the requested number per group.
+#[derive(Debug, Clone, Default)]
+pub struct Suggestions {
+    pub stories: Vec<Value>,
+    pub authors: Vec<Value>,
+    pub categories: Vec<Value>,
+}
+
+/// What `/readyz` reports about the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Readiness {
+    // `green`, `yellow` or `red`; `None` for backends without a cluster
+    pub cluster_status: Option<String>,
+    // `(entity, index, exists)`
+    pub indices: Vec<(&'static str, String, bool)>,
+}
+
+impl Readiness {
+    /// A yellow cluster is only missing replicas and still answers every
+    /// query; a red one is missing data.
+    pub fn is_ready(&self) -> bool {
+        self.cluster_status.as_deref() != Some("red") && self.indices.iter().all(|(_, _, exists)| *exists)
+    }
+}
+
+#[derive(Debug)]
+pub enum BackendError {
+    // The backend could not be reached at all
+    Unavailable(String),
+    // The backend answered with an error status
+    Status { status: StatusCode, message: String },
+    // The backend answered with something we could not make sense of
+    Invalid(String),
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::Unavailable(msg) => write!(f, "backend unavailable: {}", msg),
+            BackendError::Status { status, message } => write!(f, "backend returned {}: {}", status, message),
+            BackendError::Invalid(msg) => write!(f, "invalid backend response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<EsError> for BackendError {
+    fn from(err: EsError) -> Self {
+        match err {
+            EsError::Transport(err) => BackendError::Unavailable(err.to_string()),
+            EsError::CircuitOpen | EsError::NoNodes => BackendError::Unavailable(err.to_string()),
+            EsError::Status { status, body } => BackendError::Status { status, message: body },
+            EsError::Decode(msg) => BackendError::Invalid(msg),
+        }
+    }
+}
+
+/// Everything the handlers need from a search engine. Documents are returned
+/// as the raw JSON stored for them, so every backend must store the same
+/// shape the Elasticsearch indices hold.
+pub trait SearchBackend: Send + Sync {
+    fn search_stories(&self, query: StoryQuery) -> BackendFuture<'_, Page>;
+
+    fn story_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>>;
+
+    /// Chapters of a story ordered by their `ordered` field.
+    fn chapters_by_story_id(&self, story_id: String, page: usize, size: usize) -> BackendFuture<'_, Page>;
+
+    fn chapter_by_url_key(&self, story_key: String, chapter_key: String) -> BackendFuture<'_, Option<Value>>;
+
+    fn categories(&self, type_category: Option<String>) -> BackendFuture<'_, Vec<Value>>;
+
+    fn category_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>>;
+
+    fn author_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>>;
+
+    /// Stories, authors and categories whose titles complete `text`,
+    /// ignoring diacritics. At most `size` of each.
+    fn suggest(&self, text: String, size: usize) -> BackendFuture<'_, Suggestions>;
+
+    /// Whether the backend can serve every route right now.
+    fn readiness(&self) -> BackendFuture<'_, Readiness>;
+}
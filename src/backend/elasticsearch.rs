@@ -0,0 +1,294 @@
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use crate::backend::{BackendError, BackendFuture, Page, Readiness, SearchBackend, StoryQuery, Suggestions};
+use crate::config::IndexNames;
+use crate::es_client::EsClient;
+use crate::mappings::{STORY_SEARCH_FIELDS, SUGGEST_FIELDS};
+
+/// Serves everything from the live Elasticsearch indices.
+pub struct ElasticsearchBackend {
+    es: EsClient,
+    indices: IndexNames,
+}
+
+impl ElasticsearchBackend {
+    pub fn new(es: EsClient, indices: IndexNames) -> Self {
+        ElasticsearchBackend { es, indices }
+    }
+}
+
+impl ElasticsearchBackend {
+    // Corrections of `text` that do find stories. Only asked for once a
+    // search came up sparse, as the collate queries are not free.
+    async fn did_you_mean(&self, text: &str, size: usize) -> Result<Vec<String>, BackendError> {
+        let query = json!({
+            "size": 0,
+            "suggest": {
+                "text": text,
+                "title": did_you_mean("title", size),
+                "author": did_you_mean("author.title", size)
+            }
+        });
+
+        let mut suggestions = self.es.search(&self.indices.stories, &query).await?.suggestions;
+        suggestions.truncate(size);
+        Ok(suggestions)
+    }
+}
+
+impl SearchBackend for ElasticsearchBackend {
+    fn search_stories(&self, query: StoryQuery) -> BackendFuture<'_, Page> {
+        Box::pin(async move {
+            let from = query.page.saturating_sub(1) * query.size;
+
+            // Elasticsearch query construction
+            let mut must_clauses = vec![];
+            let mut should_clauses = vec![];
+
+            if let Some(text) = &query.text {
+                must_clauses.push(json!({
+                    "multi_match": {
+                        "query": text,
+                        "fields": STORY_SEARCH_FIELDS,
+                        "type": "best_fields",
+                        "tie_breaker": 0.3
+                    }
+                }));
+                // Exact title phrases rank first, accented or not
+                should_clauses.push(json!({ "match_phrase": { "title": { "query": text, "boost": 10 } } }));
+                should_clauses.push(json!({ "match_phrase": { "title.folded": { "query": text, "boost": 6 } } }));
+            }
+
+            if let Some(author_id) = &query.author_id {
+                must_clauses.push(json!({ "term": { "author.author_id": author_id } }));
+            }
+
+            if let Some(category_id) = &query.category_id {
+                must_clauses.push(json!({ "term": { "categories.category_id.keyword": category_id } }));
+            }
+
+            if query.is_full {
+                must_clauses.push(json!({ "term": { "is_full": true } }));
+            }
+
+            let mut es_query = json!({
+                "query": {
+                    "bool": {
+                        "must": must_clauses
+                    }
+                },
+                "from": from,
+                "size": query.size
+            });
+            if !should_clauses.is_empty() {
+                es_query["query"]["bool"]["should"] = json!(should_clauses);
+            }
+
+            // Add sorting by latest if required
+            if query.sort_by_latest {
+                es_query["sort"] = json!([{ "updated_date": { "order": "desc" } }]);
+            }
+
+            if let (Some(_), Some(highlight)) = (&query.text, &query.highlight) {
+                // The `.folded` sub-fields highlight the original text where
+                // only the query without diacritics matched. Fragments are
+                // HTML, so the text around the tags is escaped.
+                es_query["highlight"] = json!({
+                    "encoder": "html",
+                    "pre_tags": [highlight.pre_tag],
+                    "post_tags": [highlight.post_tag],
+                    "fragment_size": highlight.fragment_size,
+                    "fields": {
+                        "title": { "number_of_fragments": 0 },
+                        "title.folded": { "number_of_fragments": 0 },
+                        "description": {},
+                        "description.folded": {}
+                    }
+                });
+            }
+
+            let result = self.es.search(&self.indices.stories, &es_query).await?;
+            let suggestions = match (&query.text, query.did_you_mean) {
+                // Corrections are a bonus; the stories were found either way
+                (Some(text), Some(did_you_mean)) if did_you_mean.is_sparse(result.total) => {
+                    match self.did_you_mean(text, did_you_mean.size).await {
+                        Ok(suggestions) => Some(suggestions),
+                        Err(err) => {
+                            tracing::warn!(error = %err, "failed to look for spelling corrections");
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            let items = result.hits
+                .into_iter()
+                .map(|hit| match query.highlight {
+                    Some(_) => highlighted(hit.source, hit.highlight),
+                    None => hit.source,
+                })
+                .collect();
+
+            Ok(Page { total: result.total, items, suggestions })
+        })
+    }
+
+    fn story_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            Ok(self.es.get_one_by_term(&self.indices.stories, "url_key", &url_key).await?)
+        })
+    }
+
+    fn chapters_by_story_id(&self, story_id: String, page: usize, size: usize) -> BackendFuture<'_, Page> {
+        Box::pin(async move {
+            let query = json!({
+                "query": {
+                    "term": {
+                        "story_id": story_id
+                    }
+                },
+                "from": page.saturating_sub(1) * size,
+                "size": size,
+                "sort": [{ "ordered": { "order": "asc" } }]
+            });
+
+            let result = self.es.search(&self.indices.chapters, &query).await?;
+            Ok(Page {
+                total: result.total,
+                items: result.into_sources(),
+                suggestions: None,
+            })
+        })
+    }
+
+    fn chapter_by_url_key(&self, story_key: String, chapter_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            let query = json!({
+                "query": {
+                    "bool": {
+                        "must": [
+                            { "term": { "story_url_key": story_key }},
+                            { "term": { "url_key": chapter_key }}
+                        ]
+                    }
+                }
+            });
+
+            Ok(self.es.get_one(&self.indices.chapters, &query).await?)
+        })
+    }
+
+    fn categories(&self, type_category: Option<String>) -> BackendFuture<'_, Vec<Value>> {
+        Box::pin(async move {
+            let mut must_clauses = vec![];
+
+            // Add type_category filter if present
+            if let Some(type_category) = type_category {
+                must_clauses.push(json!({
+                    "term": { "type_category": type_category }
+                }));
+            }
+
+            let query = json!({
+                "query": {
+                    "bool": {
+                        "must": must_clauses
+                    }
+                },
+                "size": 1000 // Adjust size as needed
+            });
+
+            let result = self.es.search(&self.indices.categories, &query).await?;
+            Ok(result.into_sources())
+        })
+    }
+
+    fn category_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            Ok(self.es.get_one_by_term(&self.indices.categories, "url_key", &url_key).await?)
+        })
+    }
+
+    fn author_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            Ok(self.es.get_one_by_term(&self.indices.authors, "url_key", &url_key).await?)
+        })
+    }
+
+    fn suggest(&self, text: String, size: usize) -> BackendFuture<'_, Suggestions> {
+        Box::pin(async move {
+            let query = json!({
+                "query": {
+                    "multi_match": {
+                        "query": text,
+                        "type": "bool_prefix",
+                        "fields": SUGGEST_FIELDS
+                    }
+                },
+                "size": size,
+                "_source": ["title", "url_key"]
+            });
+
+            // One round trip for all three groups
+            let results = self.es.msearch(&[
+                (&self.indices.stories, query.clone()),
+                (&self.indices.authors, query.clone()),
+                (&self.indices.categories, query),
+            ]).await?;
+            let mut groups = results.into_iter().map(|result| result.into_sources());
+
+            Ok(Suggestions {
+                stories: groups.next().unwrap_or_default(),
+                authors: groups.next().unwrap_or_default(),
+                categories: groups.next().unwrap_or_default(),
+            })
+        })
+    }
+
+    fn readiness(&self) -> BackendFuture<'_, Readiness> {
+        Box::pin(async move {
+            let cluster_status = self.es.cluster_health().await?;
+
+            let mut indices = Vec::new();
+            for (entity, name) in self.indices.entries() {
+                let exists = !self.es.resolve_index(name).await?.is_empty();
+                indices.push((entity, name.to_string(), exists));
+            }
+
+            Ok(Readiness { cluster_status: Some(cluster_status), indices })
+        })
+    }
+}
+
+// A phrase suggester correcting the query against the terms of `field`.
+// The collate query drops corrections that would still find no story.
+fn did_you_mean(field: &str, size: usize) -> Value {
+    json!({
+        "phrase": {
+            "field": field,
+            "size": size,
+            "max_errors": 2,
+            "direct_generator": [{ "field": field, "suggest_mode": "missing" }],
+            "collate": {
+                "query": { "source": { "match": { field: { "query": "{{suggestion}}", "operator": "and" } } } },
+                "prune": false
+            }
+        }
+    })
+}
+
+// `source` with a `_highlight` object holding the fragments of `title` and
+// `description`, taken from the `.folded` sub-field when the field itself
+// has none.
+fn highlighted(mut source: Value, fragments: Option<BTreeMap<String, Vec<String>>>) -> Value {
+    let mut fragments = fragments.unwrap_or_default();
+    let mut highlight = Map::new();
+    for field in ["title", "description"] {
+        if let Some(field_fragments) = fragments.remove(field).or_else(|| fragments.remove(&format!("{}.folded", field))) {
+            highlight.insert(field.to_string(), json!(field_fragments));
+        }
+    }
+
+    source["_highlight"] = Value::Object(highlight);
+    source
+}
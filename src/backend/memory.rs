@@ -0,0 +1,431 @@
+use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::backend::{BackendFuture, Highlight, Page, Readiness, SearchBackend, StoryQuery, Suggestions};
+
+/// Keeps every document in memory, loaded from JSON fixture files. Meant for
+/// local development and tests, where no Elasticsearch cluster is around.
+///
+/// The fixture directory holds `stories.json`, `chapters.json`,
+/// `categories.json` and `authors.json`, each a JSON array of documents in
+/// the same shape as the `_source` of the matching Elasticsearch index. A
+/// missing file is treated as an empty index.
+pub struct MemoryBackend {
+    stories: Vec<Value>,
+    chapters: Vec<Value>,
+    categories: Vec<Value>,
+    authors: Vec<Value>,
+}
+
+impl MemoryBackend {
+    pub fn new(stories: Vec<Value>, chapters: Vec<Value>, categories: Vec<Value>, authors: Vec<Value>) -> Self {
+        MemoryBackend { stories, chapters, categories, authors }
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        Ok(MemoryBackend::new(
+            load_fixture(dir, "stories")?,
+            load_fixture(dir, "chapters")?,
+            load_fixture(dir, "categories")?,
+            load_fixture(dir, "authors")?,
+        ))
+    }
+}
+
+impl MemoryBackend {
+    // Lowercased words of the story titles and of the author names, the
+    // terms the phrase suggesters draw corrections from
+    fn title_words(&self) -> Vec<String> {
+        words(self.stories.iter().map(|story| &story["title"]))
+    }
+
+    fn author_words(&self) -> Vec<String> {
+        words(self.stories.iter().map(|story| &story["author"]["title"]))
+    }
+}
+
+fn words<'a>(values: impl Iterator<Item = &'a Value>) -> Vec<String> {
+    let mut words: Vec<String> = values.flat_map(|value| strings(value).into_iter().flat_map(tokenize)).collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+fn load_fixture(dir: &Path, name: &str) -> Result<Vec<Value>, String> {
+    let path = dir.join(format!("{}.json", name));
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("failed to read {}: {}", path.display(), err)),
+    };
+
+    serde_json::from_str(&contents)
+        .map_err(|err| format!("{} is not a JSON array of documents: {}", path.display(), err))
+}
+
+// Compares a document field with a string the way a `term` query would,
+// so numeric and boolean fields can be matched against path parameters.
+fn field_eq(value: &Value, expected: &str) -> bool {
+    match value {
+        Value::String(s) => s == expected,
+        Value::Number(n) => n.to_string() == expected,
+        Value::Bool(b) => b.to_string() == expected,
+        _ => false,
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+// Number of query tokens found in `text`, a rough stand-in for a `match` score.
+fn match_score(query_tokens: &[String], text: &str) -> usize {
+    let tokens = tokenize(text);
+    query_tokens.iter().filter(|token| tokens.contains(token)).count()
+}
+
+// A rough stand-in for the Elasticsearch story search: query tokens found
+// in each field, weighted like `STORY_SEARCH_FIELDS` and counted twice when
+// the diacritics match too, plus a bonus when the title holds the whole
+// query as a phrase.
+fn text_score(story: &Value, text: &str) -> usize {
+    let accented = tokenize(text);
+    let folded: Vec<String> = accented.iter().map(|token| fold(token)).collect();
+    let fields = [
+        (5, &story["title"]),
+        (4, &story["alternate_titles"]),
+        (2, &story["author"]["title"]),
+        (1, &story["description"]),
+    ];
+
+    let mut score = 0;
+    for (weight, value) in fields {
+        for field in strings(value) {
+            score += weight * (match_score(&folded, &fold(field)) + match_score(&accented, field));
+        }
+    }
+
+    let title = tokenize(&fold(story["title"].as_str().unwrap_or("")));
+    if score > 0 && !folded.is_empty() && title.windows(folded.len()).any(|window| window == folded.as_slice()) {
+        score += 20;
+    }
+    score
+}
+
+// Whether `title` holds every query token, the last one possibly still
+// being typed, like a `bool_prefix` query on a `search_as_you_type` field.
+fn completes(query: &[String], title: &str) -> bool {
+    let Some((last, complete)) = query.split_last() else {
+        return false;
+    };
+    let tokens = tokenize(&fold(title));
+
+    complete.iter().all(|token| tokens.contains(token)) && tokens.iter().any(|token| token.starts_with(last.as_str()))
+}
+
+// A rough stand-in for the phrase suggesters of the Elasticsearch story
+// search: each query token of four letters or more that is not a word of
+// `vocabulary` is replaced by the closest word starting with the same
+// letter, at most two edits away once diacritics are folded. `None` when
+// nothing needed correcting or some token could not be corrected.
+fn correct(text: &str, vocabulary: &[String]) -> Option<String> {
+    let mut corrected = false;
+    let mut words = Vec::new();
+
+    for token in tokenize(text) {
+        if token.chars().count() < 4 || vocabulary.contains(&token) {
+            words.push(token);
+            continue;
+        }
+        let folded = fold(&token);
+        let (distance, word) = vocabulary
+            .iter()
+            .map(|word| (fold(word), word))
+            .filter(|(candidate, _)| candidate.chars().next() == folded.chars().next())
+            .map(|(candidate, word)| (edit_distance(&folded, &candidate), word))
+            .min_by_key(|(distance, _)| *distance)?;
+        if distance > 2 {
+            return None;
+        }
+        corrected = true;
+        words.push(word.clone());
+    }
+
+    corrected.then(|| words.join(" "))
+}
+
+// The `_highlight` object of `story`: its whole title and a description
+// fragment, when they hold any of the (folded) query `terms`.
+fn highlighted(story: &Value, terms: &[String], highlight: &Highlight) -> Value {
+    let mut fragments = Map::new();
+    for (field, limit) in [("title", None), ("description", Some(highlight.fragment_size))] {
+        if let Some(fragment) = story[field].as_str().and_then(|text| mark(text, terms, highlight, limit)) {
+            fragments.insert(field.to_string(), json!([fragment]));
+        }
+    }
+    Value::Object(fragments)
+}
+
+// A rough stand-in for the Elasticsearch highlighter with the `html`
+// encoder: escapes `text` and wraps the words matching a query term, with
+// or without diacritics, in the tags.
+// With a `limit`, the fragment starts at the first match and ends after
+// about that many characters. `None` when nothing matched.
+fn mark(text: &str, terms: &[String], highlight: &Highlight, limit: Option<usize>) -> Option<String> {
+    // Alternating runs of word and non-word characters, `(run, is_word)`
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for c in text.chars() {
+        let word = c.is_alphanumeric();
+        match runs.last_mut() {
+            Some((run, is_word)) if *is_word == word => run.push(c),
+            _ => runs.push((c.to_string(), word)),
+        }
+    }
+
+    let matches = |run: &str, word: bool| word && terms.contains(&fold(run));
+    let first = runs.iter().position(|(run, word)| matches(run, *word))?;
+    let start = if limit.is_some() { first } else { 0 };
+
+    let mut fragment = String::new();
+    let mut length = 0;
+    for (run, word) in &runs[start..] {
+        if limit.is_some_and(|limit| length >= limit) {
+            break;
+        }
+        length += run.chars().count();
+        if matches(run, *word) {
+            fragment.push_str(&highlight.pre_tag);
+            fragment.push_str(&escape_html(run));
+            fragment.push_str(&highlight.post_tag);
+        } else {
+            fragment.push_str(&escape_html(run));
+        }
+    }
+    Some(fragment.trim_end().to_string())
+}
+
+// Escapes the characters Elasticsearch's `html` highlight encoder does.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            '/' => escaped.push_str("&#x2F;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Levenshtein distance, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// A string field, or each string of an array field.
+fn strings(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Lowercases and strips Vietnamese diacritics, like the `asciifolding`
+// analyzer of the `.folded` sub-fields.
+fn fold(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'á' | 'ả' | 'ã' | 'ạ' | 'ă' | 'ằ' | 'ắ' | 'ẳ' | 'ẵ' | 'ặ' | 'â' | 'ầ' | 'ấ' | 'ẩ' | 'ẫ' | 'ậ' => 'a',
+            'è' | 'é' | 'ẻ' | 'ẽ' | 'ẹ' | 'ê' | 'ề' | 'ế' | 'ể' | 'ễ' | 'ệ' => 'e',
+            'ì' | 'í' | 'ỉ' | 'ĩ' | 'ị' => 'i',
+            'ò' | 'ó' | 'ỏ' | 'õ' | 'ọ' | 'ô' | 'ồ' | 'ố' | 'ổ' | 'ỗ' | 'ộ' | 'ơ' | 'ờ' | 'ớ' | 'ở' | 'ỡ' | 'ợ' => 'o',
+            'ù' | 'ú' | 'ủ' | 'ũ' | 'ụ' | 'ư' | 'ừ' | 'ứ' | 'ử' | 'ữ' | 'ự' => 'u',
+            'ỳ' | 'ý' | 'ỷ' | 'ỹ' | 'ỵ' => 'y',
+            'đ' => 'd',
+            c => c,
+        })
+        .collect()
+}
+
+fn paginate(items: Vec<Value>, page: usize, size: usize) -> Page {
+    let total = items.len() as u64;
+    let items = items.into_iter().skip(page.saturating_sub(1) * size).take(size).collect();
+
+    Page { items, total, suggestions: None }
+}
+
+fn find_one(docs: &[Value], predicate: impl Fn(&Value) -> bool) -> Option<Value> {
+    docs.iter().find(|doc| predicate(doc)).cloned()
+}
+
+impl SearchBackend for MemoryBackend {
+    fn search_stories(&self, query: StoryQuery) -> BackendFuture<'_, Page> {
+        Box::pin(async move {
+            let mut matches: Vec<(usize, &Value)> = self.stories
+                .iter()
+                .filter(|story| {
+                    query.author_id.as_deref().is_none_or(|id| field_eq(&story["author"]["author_id"], id))
+                })
+                .filter(|story| {
+                    query.category_id.as_deref().is_none_or(|id| {
+                        story["categories"]
+                            .as_array()
+                            .is_some_and(|categories| categories.iter().any(|c| field_eq(&c["category_id"], id)))
+                    })
+                })
+                .filter(|story| !query.is_full || story["is_full"] == Value::Bool(true))
+                .filter_map(|story| match &query.text {
+                    Some(text) => {
+                        let score = text_score(story, text);
+                        (score > 0).then_some((score, story))
+                    }
+                    None => Some((0, story)),
+                })
+                .collect();
+
+            if query.sort_by_latest {
+                // ISO 8601 timestamps order correctly as strings
+                matches.sort_by(|(_, a), (_, b)| {
+                    b["updated_date"].as_str().cmp(&a["updated_date"].as_str())
+                });
+            } else {
+                matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+            }
+
+            let stories = matches.into_iter().map(|(_, story)| story.clone()).collect();
+            let mut page = paginate(stories, query.page, query.size);
+
+            if let Some(highlight) = &query.highlight {
+                let terms = query.text.as_deref().map(|text| tokenize(&fold(text))).unwrap_or_default();
+                for story in &mut page.items {
+                    story["_highlight"] = highlighted(story, &terms, highlight);
+                }
+            }
+
+            let sparse = query.did_you_mean.filter(|did_you_mean| did_you_mean.is_sparse(page.total));
+            if let (Some(text), Some(did_you_mean)) = (query.text.as_deref(), sparse) {
+                let mut suggestions = Vec::new();
+                for vocabulary in [self.title_words(), self.author_words()] {
+                    let Some(suggestion) = correct(text, &vocabulary) else { continue };
+                    // Like the collate query, keep only corrections that find a story
+                    if !suggestions.contains(&suggestion) && self.stories.iter().any(|story| text_score(story, &suggestion) > 0) {
+                        suggestions.push(suggestion);
+                    }
+                }
+                suggestions.truncate(did_you_mean.size);
+                page.suggestions = Some(suggestions);
+            }
+
+            Ok(page)
+        })
+    }
+
+    fn story_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            Ok(find_one(&self.stories, |story| field_eq(&story["url_key"], &url_key)))
+        })
+    }
+
+    fn chapters_by_story_id(&self, story_id: String, page: usize, size: usize) -> BackendFuture<'_, Page> {
+        Box::pin(async move {
+            let mut chapters: Vec<Value> = self.chapters
+                .iter()
+                .filter(|chapter| field_eq(&chapter["story_id"], &story_id))
+                .cloned()
+                .collect();
+
+            chapters.sort_by(|a, b| {
+                a["ordered"].as_f64()
+                    .partial_cmp(&b["ordered"].as_f64())
+                    .unwrap_or(Ordering::Equal)
+            });
+
+            Ok(paginate(chapters, page, size))
+        })
+    }
+
+    fn chapter_by_url_key(&self, story_key: String, chapter_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            Ok(find_one(&self.chapters, |chapter| {
+                field_eq(&chapter["story_url_key"], &story_key) && field_eq(&chapter["url_key"], &chapter_key)
+            }))
+        })
+    }
+
+    fn categories(&self, type_category: Option<String>) -> BackendFuture<'_, Vec<Value>> {
+        Box::pin(async move {
+            Ok(self.categories
+                .iter()
+                .filter(|category| {
+                    type_category.as_deref().is_none_or(|t| field_eq(&category["type_category"], t))
+                })
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn category_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            Ok(find_one(&self.categories, |category| field_eq(&category["url_key"], &url_key)))
+        })
+    }
+
+    fn author_by_url_key(&self, url_key: String) -> BackendFuture<'_, Option<Value>> {
+        Box::pin(async move {
+            Ok(find_one(&self.authors, |author| field_eq(&author["url_key"], &url_key)))
+        })
+    }
+
+    fn suggest(&self, text: String, size: usize) -> BackendFuture<'_, Suggestions> {
+        Box::pin(async move {
+            let query = tokenize(&fold(&text));
+            let complete = |docs: &[Value]| -> Vec<Value> {
+                docs.iter()
+                    .filter(|doc| completes(&query, doc["title"].as_str().unwrap_or("")))
+                    .take(size)
+                    .cloned()
+                    .collect()
+            };
+
+            Ok(Suggestions {
+                stories: complete(&self.stories),
+                authors: complete(&self.authors),
+                categories: complete(&self.categories),
+            })
+        })
+    }
+
+    // Everything was loaded at startup, so the fixtures are always there
+    fn readiness(&self) -> BackendFuture<'_, Readiness> {
+        Box::pin(async move {
+            let indices = ["stories", "chapters", "categories", "authors"]
+                .into_iter()
+                .map(|entity| (entity, entity.to_string(), true))
+                .collect();
+
+            Ok(Readiness { cluster_status: None, indices })
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use hyper::header::{HeaderName, HeaderValue, CACHE_CONTROL};
+use hyper::{Body, Response};
+use std::time::Duration;
+
+/// Tags a CDN can purge by, space separated (Fastly, Varnish xkey).
+pub static SURROGATE_KEY: HeaderName = HeaderName::from_static("surrogate-key");
+
+pub const MINUTE: u32 = 60;
+pub const HOUR: u32 = 60 * MINUTE;
+pub const DAY: u32 = 24 * HOUR;
+
+/// How long browsers and shared caches may keep a route's successful
+/// responses. Declared per route in the router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    max_age: u32,
+    s_maxage: Option<u32>,
+    stale_while_revalidate: Option<u32>,
+    surrogate_key: Option<&'static str>,
+}
+
+impl CachePolicy {
+    /// Cacheable by anyone for `max_age` seconds.
+    pub const fn public(max_age: u32) -> Self {
+        CachePolicy { max_age, s_maxage: None, stale_while_revalidate: None, surrogate_key: None }
+    }
+
+    /// A separate lifetime for shared caches such as the CDN.
+    pub const fn s_maxage(mut self, secs: u32) -> Self {
+        self.s_maxage = Some(secs);
+        self
+    }
+
+    /// How long an expired response may still be served while it is
+    /// refreshed in the background.
+    pub const fn stale_while_revalidate(mut self, secs: u32) -> Self {
+        self.stale_while_revalidate = Some(secs);
+        self
+    }
+
+    /// A tag added to every response of the route, on top of the ones the
+    /// handler sets.
+    pub const fn surrogate_key(mut self, key: &'static str) -> Self {
+        self.surrogate_key = Some(key);
+        self
+    }
+
+    /// How long the response cache may keep a response: as long as the CDN.
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(u64::from(self.s_maxage.unwrap_or(self.max_age)))
+    }
+
+    pub fn cache_control(&self) -> String {
+        let mut directives = vec![format!("public, max-age={}", self.max_age)];
+        if let Some(secs) = self.s_maxage {
+            directives.push(format!("s-maxage={}", secs));
+        }
+        if let Some(secs) = self.stale_while_revalidate {
+            directives.push(format!("stale-while-revalidate={}", secs));
+        }
+        directives.join(", ")
+    }
+
+    /// Stamps the policy on a successful response. A `Cache-Control` set by
+    /// the handler wins.
+    pub fn apply(&self, response: &mut Response<Body>) {
+        let headers = response.headers_mut();
+        if !headers.contains_key(CACHE_CONTROL) {
+            if let Ok(value) = HeaderValue::from_str(&self.cache_control()) {
+                headers.insert(CACHE_CONTROL, value);
+            }
+        }
+        if let Some(key) = self.surrogate_key {
+            add_surrogate_keys(response, [key.to_string()]);
+        }
+    }
+}
+
+/// Appends `keys` to the response's `Surrogate-Key` header.
+pub fn add_surrogate_keys(response: &mut Response<Body>, keys: impl IntoIterator<Item = String>) {
+    let headers = response.headers_mut();
+    let mut tags: Vec<String> = headers
+        .get(&SURROGATE_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    for key in keys {
+        if !key.is_empty() && !tags.contains(&key) {
+            tags.push(key);
+        }
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&tags.join(" ")) {
+        headers.insert(SURROGATE_KEY.clone(), value);
+    }
+}
+
+/// `story-<id>` for a story document, or `None` without a usable id.
+pub fn story_key(story_id: &serde_json::Value) -> Option<String> {
+    tag("story", story_id)
+}
+
+/// `chapter-<id>` for a chapter document, or `None` without a usable id.
+pub fn chapter_key(chapter_id: &serde_json::Value) -> Option<String> {
+    tag("chapter", chapter_id)
+}
+
+// Ids are strings in newer documents and numbers in older ones. Anything
+// that could break the space separated header is dropped.
+fn tag(kind: &str, id: &serde_json::Value) -> Option<String> {
+    let id = match id {
+        serde_json::Value::String(id) => id.clone(),
+        serde_json::Value::Number(id) => id.to_string(),
+        _ => return None,
+    };
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return None;
+    }
+    Some(format!("{}-{}", kind, id))
+}
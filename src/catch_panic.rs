@@ -0,0 +1,38 @@
+use futures_util::FutureExt;
+use hyper::{Body, Response};
+use serde_json::json;
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use crate::error::AppError;
+use crate::request_id::X_REQUEST_ID;
+
+/// Runs one request to completion. A panic anywhere inside `future` is
+/// caught and answered with a 500 `internal_error` carrying `request_id`,
+/// instead of tearing down the connection.
+pub async fn catch_panic<F>(request_id: &str, future: F) -> Response<Body>
+where
+    F: Future<Output = Response<Body>>,
+{
+    match AssertUnwindSafe(future).catch_unwind().await {
+        Ok(response) => response,
+        Err(panic) => {
+            tracing::error!(panic = panic_message(&*panic), "request handler panicked");
+
+            AppError::internal("Internal server error")
+                .with_data(json!({ "request_id": request_id }))
+                .with_header(X_REQUEST_ID.clone(), request_id)
+                .into_response()
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "non-string panic payload"
+    }
+}
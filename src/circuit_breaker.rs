@@ -0,0 +1,97 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // Requests flow; counts failures in a row
+    Closed { failures: u32 },
+    // Requests fail fast until `until`
+    Open { until: Instant },
+    // One probe request is in flight; everything else fails fast
+    HalfOpen,
+}
+
+/// Stops calling an upstream that keeps failing. After `failure_threshold`
+/// failures in a row the breaker opens and [`CircuitBreaker::try_acquire`]
+/// refuses calls for `open_for`. Then a single probe is let through: its
+/// success closes the breaker, its failure opens it again.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_for: Duration,
+    state: Mutex<State>,
+}
+
+/// Permission to make one call. Report how it went with
+/// [`CircuitBreaker::record`]; a permit dropped without a report (the call
+/// was cancelled) counts as a failure if it was the probe.
+#[derive(Debug)]
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl CircuitBreaker {
+    /// A `failure_threshold` of zero disables the breaker.
+    pub fn new(failure_threshold: u32, open_for: Duration) -> CircuitBreaker {
+        CircuitBreaker { failure_threshold, open_for, state: Mutex::new(State::Closed { failures: 0 }) }
+    }
+
+    /// `None` while the breaker is open.
+    pub fn try_acquire(&self) -> Option<Permit<'_>> {
+        if self.failure_threshold == 0 {
+            return Some(Permit { breaker: self, probe: false, recorded: false });
+        }
+
+        let mut state = self.lock();
+        match *state {
+            State::Closed { .. } => Some(Permit { breaker: self, probe: false, recorded: false }),
+            State::Open { until } if Instant::now() >= until => {
+                *state = State::HalfOpen;
+                Some(Permit { breaker: self, probe: true, recorded: false })
+            }
+            State::Open { .. } | State::HalfOpen => None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.lock(), State::Closed { .. })
+    }
+
+    pub fn record(&self, mut permit: Permit<'_>, success: bool) {
+        permit.recorded = true;
+        self.update(success);
+    }
+
+    fn update(&self, success: bool) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+
+        let mut state = self.lock();
+        *state = match (*state, success) {
+            (_, true) => State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 < self.failure_threshold => {
+                State::Closed { failures: failures + 1 }
+            }
+            (State::Open { until }, false) => State::Open { until },
+            (_, false) => {
+                tracing::warn!(open_for_ms = self.open_for.as_millis() as u64, "circuit breaker opened");
+                State::Open { until: Instant::now() + self.open_for }
+            }
+        };
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.breaker.update(false);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder, ZstdEncoder};
+use async_compression::Level;
+use futures_util::TryStreamExt;
+use tokio::io::AsyncRead;
+use hyper::body::HttpBody;
+use hyper::header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, VARY};
+use hyper::{Body, Response, StatusCode};
+use std::io;
+use std::sync::Arc;
+use tokio_util::io::{ReaderStream, StreamReader};
+use crate::config::CompressionConfig;
+use crate::metrics::Metrics;
+
+/// A content coding this server can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Zstd,
+    Gzip,
+}
+
+impl Encoding {
+    // Preferred first when the client weighs several codings equally
+    pub const ALL: [Encoding; 3] = [Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn matches(&self, coding: &str) -> bool {
+        coding.eq_ignore_ascii_case(self.as_str())
+            || (*self == Encoding::Gzip && coding.eq_ignore_ascii_case("x-gzip"))
+    }
+}
+
+/// The `ETag` of the `encoding` representation of a response tagged
+/// `etag`. A strong validator names one exact byte sequence, so each coding
+/// gets its own: `"<tag>-gzip"`. Weak validators are left as they are.
+pub fn encoded_etag(etag: &str, encoding: Encoding) -> Option<String> {
+    if etag.starts_with("W/") {
+        return None;
+    }
+    let tag = etag.strip_suffix('"')?;
+    Some(format!("{}-{}\"", tag, encoding.as_str()))
+}
+
+/// `etag` without the coding suffix [`encoded_etag`] adds, so a validator
+/// of a compressed representation matches the identity one.
+pub fn decoded_etag(etag: &str) -> String {
+    let Some(tag) = etag.strip_suffix('"') else {
+        return etag.to_string();
+    };
+    let tag = Encoding::ALL
+        .iter()
+        .find_map(|encoding| tag.strip_suffix(&format!("-{}", encoding.as_str())))
+        .unwrap_or(tag);
+    format!("{}\"", tag)
+}
+
+/// Picks the coding to use for an `Accept-Encoding` header, honouring
+/// q-values. A coding with `q=0` is never chosen; `*` stands for every
+/// coding the client did not list. Returns `None` when the client accepts
+/// none of ours, in which case the body is sent as is.
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut offers: Vec<(&str, f32)> = Vec::new();
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim();
+        if coding.is_empty() {
+            continue;
+        }
+        let q = parts
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .map(|(_, value)| value.trim().parse::<f32>().unwrap_or(0.0))
+            .unwrap_or(1.0);
+        offers.push((coding, q));
+    }
+
+    let wildcard = offers.iter().find(|(coding, _)| *coding == "*").map(|(_, q)| *q);
+    let quality = |encoding: Encoding| {
+        offers
+            .iter()
+            .find(|(coding, _)| encoding.matches(coding))
+            .map(|(_, q)| *q)
+            .or(wildcard)
+            .unwrap_or(0.0)
+    };
+
+    let mut best: Option<(Encoding, f32)> = None;
+    for encoding in Encoding::ALL {
+        let q = quality(encoding);
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((encoding, q));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Compresses `response` on the fly with the coding negotiated from
+/// `accept_encoding`. Responses without a body, smaller than `min_size`,
+/// already encoded or of an already compressed media type pass through
+/// unchanged. Every compressible response gets `Vary: Accept-Encoding`, so
+/// caches keep the variants apart. Bytes in and out are counted in
+/// `metrics` as the body streams.
+pub fn compress(
+    mut response: Response<Body>,
+    accept_encoding: Option<&HeaderValue>,
+    config: &CompressionConfig,
+    metrics: &Arc<Metrics>,
+) -> Response<Body> {
+    if !config.enabled || !is_compressible(&response) {
+        return response;
+    }
+    response.headers_mut().append(VARY, HeaderValue::from_static("accept-encoding"));
+
+    // Bodies of unknown length are streamed, so they are always worth it
+    if response.body().size_hint().exact().is_some_and(|len| len < config.min_size as u64) {
+        return response;
+    }
+    let encoding = match accept_encoding.and_then(|value| value.to_str().ok()).and_then(negotiate) {
+        Some(encoding) => encoding,
+        None => return response,
+    };
+
+    let body = std::mem::take(response.body_mut());
+    let input_metrics = metrics.clone();
+    let body = TryStreamExt::map_err(body, io::Error::other)
+        .inspect_ok(move |chunk| input_metrics.add_compression_input(encoding, chunk.len()));
+    let reader = StreamReader::new(body);
+    let metrics = metrics.clone();
+    *response.body_mut() = match encoding {
+        Encoding::Brotli => {
+            let encoder = BrotliEncoder::with_quality(reader, Level::Precise(config.brotli_level));
+            counted_body(encoder, encoding, metrics)
+        }
+        Encoding::Zstd => {
+            let encoder = ZstdEncoder::with_quality(reader, Level::Precise(config.zstd_level));
+            counted_body(encoder, encoding, metrics)
+        }
+        Encoding::Gzip => {
+            let encoder = GzipEncoder::with_quality(reader, Level::Precise(config.gzip_level));
+            counted_body(encoder, encoding, metrics)
+        }
+    };
+
+    let headers = response.headers_mut();
+    headers.remove(CONTENT_LENGTH);
+    headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.as_str()));
+    let etag = headers.get(ETAG).and_then(|value| value.to_str().ok()).and_then(|etag| encoded_etag(etag, encoding));
+    if let Some(etag) = etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+        headers.insert(ETAG, etag);
+    }
+    response
+}
+
+fn counted_body(encoder: impl AsyncRead + Send + 'static, encoding: Encoding, metrics: Arc<Metrics>) -> Body {
+    let stream = ReaderStream::new(encoder)
+        .inspect_ok(move |chunk| metrics.add_compression_output(encoding, chunk.len()));
+    Body::wrap_stream(stream)
+}
+
+fn is_compressible(response: &Response<Body>) -> bool {
+    if matches!(response.status(), StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED)
+        || response.headers().contains_key(CONTENT_ENCODING)
+        || response.body().is_end_stream()
+    {
+        return false;
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    !is_compressed_media_type(content_type)
+}
+
+// Media types whose payload is already compressed; encoding them again only
+// costs CPU.
+fn is_compressed_media_type(content_type: &str) -> bool {
+    let media_type = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+
+    media_type.starts_with("image/") && media_type != "image/svg+xml"
+        || media_type.starts_with("video/")
+        || media_type.starts_with("audio/")
+        || matches!(
+            media_type.as_str(),
+            "application/zip"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/zstd"
+                | "application/x-brotli"
+                | "application/x-7z-compressed"
+                | "application/x-rar-compressed"
+                | "application/pdf"
+                | "font/woff"
+                | "font/woff2"
+        )
+}
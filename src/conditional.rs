@@ -0,0 +1,94 @@
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use hyper::{Body, Response, StatusCode};
+use crate::compression;
+
+/// The request's conditional headers, kept after the request itself has
+/// been handed to the router.
+#[derive(Debug, Clone, Default)]
+pub struct Preconditions {
+    if_none_match: Option<HeaderValue>,
+    if_modified_since: Option<HeaderValue>,
+    accept_encoding: Option<HeaderValue>,
+}
+
+impl Preconditions {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Preconditions {
+            if_none_match: headers.get(IF_NONE_MATCH).cloned(),
+            if_modified_since: headers.get(IF_MODIFIED_SINCE).cloned(),
+            accept_encoding: headers.get(ACCEPT_ENCODING).cloned(),
+        }
+    }
+
+    /// Turns a `200` carrying validators into a bodiless `304 Not Modified`
+    /// when the client's copy is still current. As RFC 9110 requires,
+    /// `If-Modified-Since` is ignored when `If-None-Match` is present.
+    pub fn apply(&self, mut response: Response<Body>) -> Response<Body> {
+        if response.status() != StatusCode::OK || !self.not_modified(&response) {
+            return response;
+        }
+
+        // The 304 names the representation the client would get, which for
+        // a compressed one carries the coding suffix
+        let held = self.held_etag(&response);
+
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        *response.body_mut() = Body::empty();
+        let headers = response.headers_mut();
+        headers.remove(CONTENT_LENGTH);
+        headers.remove(CONTENT_TYPE);
+        if let Some(held) = held {
+            headers.insert(ETAG, held);
+        }
+        response
+    }
+
+    // The tag of the compressed representation the client negotiated, if
+    // it holds that one. Compression skips 304s, so it is set here.
+    fn held_etag(&self, response: &Response<Body>) -> Option<HeaderValue> {
+        let etag = response.headers().get(ETAG)?.to_str().ok()?;
+        let encoding = compression::negotiate(self.accept_encoding.as_ref()?.to_str().ok()?)?;
+        let encoded = compression::encoded_etag(etag, encoding)?;
+        let candidates = self.if_none_match.as_ref()?.to_str().ok()?;
+
+        candidates
+            .split(',')
+            .any(|candidate| candidate.trim().trim_start_matches("W/") == encoded)
+            .then(|| HeaderValue::from_str(&encoded).ok())?
+    }
+
+    fn not_modified(&self, response: &Response<Body>) -> bool {
+        let headers = response.headers();
+
+        if let Some(if_none_match) = &self.if_none_match {
+            let etag = match headers.get(ETAG).and_then(|value| value.to_str().ok()) {
+                Some(etag) => etag,
+                None => return false,
+            };
+            return if_none_match.to_str().is_ok_and(|candidates| etag_matches(candidates, etag));
+        }
+
+        match (&self.if_modified_since, headers.get(LAST_MODIFIED)) {
+            (Some(since), Some(last_modified)) => {
+                let since = since.to_str().ok().and_then(|value| httpdate::parse_http_date(value).ok());
+                let last_modified = last_modified.to_str().ok().and_then(|value| httpdate::parse_http_date(value).ok());
+                matches!((since, last_modified), (Some(since), Some(last_modified)) if last_modified <= since)
+            }
+            _ => false,
+        }
+    }
+}
+
+// `If-None-Match` uses the weak comparison: `W/"x"` matches `"x"`. The
+// tag of a compressed representation, `"x-gzip"`, matches `"x"` too.
+fn etag_matches(candidates: &str, etag: &str) -> bool {
+    let etag = opaque(etag);
+
+    candidates.split(',').any(|candidate| candidate.trim() == "*" || opaque(candidate) == etag)
+}
+
+fn opaque(tag: &str) -> String {
+    compression::decoded_etag(tag.trim().trim_start_matches("W/"))
+}
@@ -0,0 +1,827 @@
+use hyper::header::HeaderName;
+use hyper::Method;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// File read when `CONFIG_FILE` is not set. It is optional.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    Elasticsearch,
+    Memory,
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "elasticsearch" => Ok(BackendKind::Elasticsearch),
+            "memory" => Ok(BackendKind::Memory),
+            other => Err(format!("unknown backend `{}`, expected `elasticsearch` or `memory`", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // One JSON object per line, for log shippers
+    Json,
+    // Human readable, for local development
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(LogFormat::Json),
+            "text" => Ok(LogFormat::Text),
+            other => Err(format!("unknown log format `{}`, expected `json` or `text`", other)),
+        }
+    }
+}
+
+/// Everything needed to start the service. Built once at startup by
+/// [`Config::load`]; every section falls back to its defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub backend: BackendConfig,
+    pub elasticsearch: ElasticsearchConfig,
+    pub cors: CorsConfig,
+    pub compression: CompressionConfig,
+    pub cache: ResponseCacheConfig,
+    pub pagination: PaginationConfig,
+    pub search: SearchConfig,
+    pub logging: LoggingConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen_addr: SocketAddr,
+    // After a shutdown signal, how long to keep accepting connections while
+    // `/readyz` fails, so load balancers stop routing here first. At least
+    // one readiness probe period, or no probe sees the failure.
+    pub shutdown_delay_ms: u64,
+    // How long in-flight requests may take to finish before exiting anyway
+    pub shutdown_timeout_ms: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen_addr: ([0, 0, 0, 0], 8084).into(),
+            shutdown_delay_ms: 5_000,
+            shutdown_timeout_ms: 30_000,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn shutdown_delay(&self) -> Duration {
+        Duration::from_millis(self.shutdown_delay_ms)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_millis(self.shutdown_timeout_ms)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    // Where the memory backend reads its JSON fixtures from
+    pub fixtures_dir: PathBuf,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        BackendConfig {
+            kind: BackendKind::Elasticsearch,
+            fixtures_dir: PathBuf::from("fixtures"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ElasticsearchConfig {
+    // One or more nodes of the same cluster; a single string is accepted
+    #[serde(alias = "url", deserialize_with = "string_or_list")]
+    pub urls: Vec<String>,
+    // Basic auth is only sent when both are set
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub connect_timeout_ms: u64,
+    pub request_timeout_ms: u64,
+    // Prepended to every index name, e.g. `staging_` to share a cluster
+    // between environments
+    pub index_prefix: String,
+    // Index or alias name per entity
+    pub indices: IndexNames,
+    pub retry: RetryConfig,
+    pub circuit_breaker: CircuitBreakerConfig,
+    // How often nodes marked down are re-checked
+    pub health_check_interval_ms: u64,
+    // Discover the cluster's other HTTP nodes through `_nodes/http`
+    pub sniff: bool,
+    pub sniff_interval_ms: u64,
+}
+
+impl Default for ElasticsearchConfig {
+    fn default() -> Self {
+        ElasticsearchConfig {
+            urls: vec!["http://localhost:9200".to_string()],
+            username: None,
+            password: None,
+            connect_timeout_ms: 2_000,
+            request_timeout_ms: 10_000,
+            index_prefix: String::new(),
+            indices: IndexNames::default(),
+            retry: RetryConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            health_check_interval_ms: 10_000,
+            sniff: false,
+            sniff_interval_ms: 300_000,
+        }
+    }
+}
+
+impl ElasticsearchConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms)
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms)
+    }
+
+    pub fn health_check_interval(&self) -> Duration {
+        Duration::from_millis(self.health_check_interval_ms)
+    }
+
+    pub fn sniff_interval(&self) -> Duration {
+        Duration::from_millis(self.sniff_interval_ms)
+    }
+
+    /// The names to query, with `index_prefix` applied.
+    pub fn index_names(&self) -> IndexNames {
+        let prefixed = |name: &str| format!("{}{}", self.index_prefix, name);
+
+        IndexNames {
+            stories: prefixed(&self.indices.stories),
+            chapters: prefixed(&self.indices.chapters),
+            categories: prefixed(&self.indices.categories),
+            authors: prefixed(&self.indices.authors),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexNames {
+    pub stories: String,
+    pub chapters: String,
+    pub categories: String,
+    pub authors: String,
+}
+
+impl IndexNames {
+    /// `(entity, index)` pairs, in a stable order.
+    pub fn entries(&self) -> [(&'static str, &str); 4] {
+        [
+            ("stories", &self.stories),
+            ("chapters", &self.chapters),
+            ("categories", &self.categories),
+            ("authors", &self.authors),
+        ]
+    }
+}
+
+impl Default for IndexNames {
+    fn default() -> Self {
+        IndexNames {
+            stories: "stories".to_string(),
+            chapters: "chapters".to_string(),
+            categories: "categories".to_string(),
+            authors: "authors".to_string(),
+        }
+    }
+}
+
+/// Retries of failed searches. Only transport errors and `429`/`502`/`503`/
+/// `504` answers are retried; every query we send is read-only.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    // Base delay, doubled on every attempt and randomized
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig { max_retries: 2, backoff_ms: 100, max_backoff_ms: 2_000 }
+    }
+}
+
+impl RetryConfig {
+    /// Upper bound of the delay before retry number `attempt` (0-based).
+    pub fn max_backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff_ms.saturating_mul(1u64 << attempt.min(20));
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    // Failed calls in a row that open the breaker, however many retries
+    // each made; 0 disables it
+    pub failure_threshold: u32,
+    // How long requests fail fast before a probe is let through
+    pub open_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig { failure_threshold: 5, open_ms: 30_000 }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn open_for(&self) -> Duration {
+        Duration::from_millis(self.open_ms)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    // `*`, an exact origin such as `https://example.com`, or every
+    // subdomain of one, such as `https://*.example.com`
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    // Lets browsers send cookies; requires explicit origins
+    pub allow_credentials: bool,
+    // How long browsers may cache a preflight answer
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            allowed_methods: vec!["GET".to_string()],
+            allowed_headers: vec!["Content-Type".to_string()],
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    // Bodies smaller than this many bytes are sent uncompressed
+    pub min_size: usize,
+    // 1 (fastest) to 9 (smallest)
+    pub gzip_level: i32,
+    // 0 (fastest) to 11 (smallest)
+    pub brotli_level: i32,
+    // 1 (fastest) to 22 (smallest)
+    pub zstd_level: i32,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            enabled: true,
+            min_size: 1024,
+            gzip_level: 6,
+            brotli_level: 4,
+            zstd_level: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResponseCacheConfig {
+    pub enabled: bool,
+    pub max_entries: usize,
+    // Upper bound on the per-route lifetimes declared in the router
+    pub max_ttl_secs: u64,
+    // Serve the last good response when the search service fails
+    pub stale_if_error: bool,
+    // How long after expiring a response may still be served stale
+    pub stale_max_age_secs: u64,
+    // Also keep last good responses on disk, to survive restarts
+    pub stale_dir: Option<PathBuf>,
+    // Files kept in `stale_dir` at most, one per cache key
+    pub stale_max_files: usize,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        ResponseCacheConfig {
+            enabled: true,
+            max_entries: 1000,
+            max_ttl_secs: 300,
+            stale_if_error: true,
+            stale_max_age_secs: 86_400,
+            stale_dir: None,
+            stale_max_files: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaginationConfig {
+    pub default_page_size: usize,
+    pub default_chapter_page_size: usize,
+    pub max_page_size: usize,
+    // Upper bound for `page * size`, like Elasticsearch's `index.max_result_window`
+    pub max_result_window: usize,
+    // Suggestions per group (stories, authors, categories) in `/search/suggest`
+    pub default_suggest_size: usize,
+    pub max_suggest_size: usize,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        PaginationConfig {
+            default_page_size: 10,
+            default_chapter_page_size: 50,
+            max_page_size: 100,
+            max_result_window: 10_000,
+            default_suggest_size: 5,
+            max_suggest_size: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    // A text search finding fewer stories than this answers with spelling
+    // corrections of the query too
+    pub did_you_mean_below: u64,
+    // Corrections offered at most; 0 turns them off
+    pub did_you_mean_size: usize,
+    // Markup around matched terms in `highlight=true` fragments
+    pub highlight_pre_tag: String,
+    pub highlight_post_tag: String,
+    // Characters per description fragment; titles are highlighted whole
+    pub highlight_fragment_size: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            did_you_mean_below: 3,
+            did_you_mean_size: 3,
+            highlight_pre_tag: "<em>".to_string(),
+            highlight_post_tag: "</em>".to_string(),
+            highlight_fragment_size: 150,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    // A filter such as `info` or `info,comic_es::es_client=debug`
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig { level: "info".to_string(), format: LogFormat::Json }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    // The config file could not be read or parsed
+    File { path: PathBuf, message: String },
+    // An environment variable holds a value of the wrong type
+    Env { var: &'static str, message: String },
+    // The values parsed fine but do not make sense together
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::File { path, message } => write!(f, "{}: {}", path.display(), message),
+            ConfigError::Env { var, message } => write!(f, "{}: {}", var, message),
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the configuration from, in increasing order of precedence: the
+    /// built-in defaults, the TOML file named by `CONFIG_FILE` (or
+    /// `config.toml` if present), and environment variables. Call `dotenv`
+    /// first so `.env` entries count as environment variables.
+    pub fn load() -> Result<Config, ConfigError> {
+        let mut config = match std::env::var("CONFIG_FILE") {
+            Ok(path) => Config::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => Config::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            Err(_) => Config::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let file_error = |message: String| ConfigError::File { path: path.to_path_buf(), message };
+
+        let contents = fs::read_to_string(path).map_err(|err| file_error(err.to_string()))?;
+        toml::from_str(&contents).map_err(|err| file_error(err.to_string()))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        if let Some(addr) = env_parse("LISTEN_ADDR")? {
+            self.server.listen_addr = addr;
+        }
+        if let Some(ms) = env_parse("SHUTDOWN_DELAY_MS")? {
+            self.server.shutdown_delay_ms = ms;
+        }
+        if let Some(ms) = env_parse("SHUTDOWN_TIMEOUT_MS")? {
+            self.server.shutdown_timeout_ms = ms;
+        }
+
+        if let Some(kind) = env_parse("SEARCH_BACKEND")? {
+            self.backend.kind = kind;
+        }
+        if let Some(dir) = env_string("FIXTURES_DIR") {
+            self.backend.fixtures_dir = PathBuf::from(dir);
+        }
+
+        let es = &mut self.elasticsearch;
+        if let Some(urls) = env_string("ES_HOST") {
+            es.urls = split_list(&urls);
+        }
+        if let Some(username) = env_string("ES_USERNAME") {
+            es.username = Some(username);
+        }
+        if let Some(password) = env_string("ES_PASSWORD") {
+            es.password = Some(password);
+        }
+        if let Some(ms) = env_parse("ES_CONNECT_TIMEOUT_MS")? {
+            es.connect_timeout_ms = ms;
+        }
+        if let Some(ms) = env_parse("ES_REQUEST_TIMEOUT_MS")? {
+            es.request_timeout_ms = ms;
+        }
+        if let Some(prefix) = env_string("ES_INDEX_PREFIX") {
+            es.index_prefix = prefix;
+        }
+        if let Some(index) = env_string("ES_INDEX_STORIES") {
+            es.indices.stories = index;
+        }
+        if let Some(index) = env_string("ES_INDEX_CHAPTERS") {
+            es.indices.chapters = index;
+        }
+        if let Some(index) = env_string("ES_INDEX_CATEGORIES") {
+            es.indices.categories = index;
+        }
+        if let Some(index) = env_string("ES_INDEX_AUTHORS") {
+            es.indices.authors = index;
+        }
+        if let Some(retries) = env_parse("ES_MAX_RETRIES")? {
+            es.retry.max_retries = retries;
+        }
+        if let Some(ms) = env_parse("ES_RETRY_BACKOFF_MS")? {
+            es.retry.backoff_ms = ms;
+        }
+        if let Some(ms) = env_parse("ES_RETRY_MAX_BACKOFF_MS")? {
+            es.retry.max_backoff_ms = ms;
+        }
+        if let Some(threshold) = env_parse("ES_BREAKER_FAILURE_THRESHOLD")? {
+            es.circuit_breaker.failure_threshold = threshold;
+        }
+        if let Some(ms) = env_parse("ES_BREAKER_OPEN_MS")? {
+            es.circuit_breaker.open_ms = ms;
+        }
+        if let Some(ms) = env_parse("ES_HEALTH_CHECK_INTERVAL_MS")? {
+            es.health_check_interval_ms = ms;
+        }
+        if let Some(sniff) = env_parse("ES_SNIFF")? {
+            es.sniff = sniff;
+        }
+        if let Some(ms) = env_parse("ES_SNIFF_INTERVAL_MS")? {
+            es.sniff_interval_ms = ms;
+        }
+
+        let cors = &mut self.cors;
+        if let Some(origins) = env_string("CORS_ALLOWED_ORIGINS") {
+            cors.allowed_origins = split_list(&origins);
+        }
+        if let Some(methods) = env_string("CORS_ALLOWED_METHODS") {
+            cors.allowed_methods = split_list(&methods);
+        }
+        if let Some(headers) = env_string("CORS_ALLOWED_HEADERS") {
+            cors.allowed_headers = split_list(&headers);
+        }
+        if let Some(credentials) = env_parse("CORS_ALLOW_CREDENTIALS")? {
+            cors.allow_credentials = credentials;
+        }
+        if let Some(secs) = env_parse("CORS_MAX_AGE_SECS")? {
+            cors.max_age_secs = secs;
+        }
+
+        let compression = &mut self.compression;
+        if let Some(enabled) = env_parse("COMPRESSION_ENABLED")? {
+            compression.enabled = enabled;
+        }
+        if let Some(size) = env_parse("COMPRESSION_MIN_SIZE")? {
+            compression.min_size = size;
+        }
+        if let Some(level) = env_parse("COMPRESSION_GZIP_LEVEL")? {
+            compression.gzip_level = level;
+        }
+        if let Some(level) = env_parse("COMPRESSION_BROTLI_LEVEL")? {
+            compression.brotli_level = level;
+        }
+        if let Some(level) = env_parse("COMPRESSION_ZSTD_LEVEL")? {
+            compression.zstd_level = level;
+        }
+
+        let cache = &mut self.cache;
+        if let Some(enabled) = env_parse("RESPONSE_CACHE_ENABLED")? {
+            cache.enabled = enabled;
+        }
+        if let Some(entries) = env_parse("RESPONSE_CACHE_MAX_ENTRIES")? {
+            cache.max_entries = entries;
+        }
+        if let Some(secs) = env_parse("RESPONSE_CACHE_MAX_TTL_SECS")? {
+            cache.max_ttl_secs = secs;
+        }
+        if let Some(stale) = env_parse("RESPONSE_CACHE_STALE_IF_ERROR")? {
+            cache.stale_if_error = stale;
+        }
+        if let Some(secs) = env_parse("RESPONSE_CACHE_STALE_MAX_AGE_SECS")? {
+            cache.stale_max_age_secs = secs;
+        }
+        if let Some(dir) = env_string("RESPONSE_CACHE_STALE_DIR") {
+            cache.stale_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(files) = env_parse("RESPONSE_CACHE_STALE_MAX_FILES")? {
+            cache.stale_max_files = files;
+        }
+
+        let pagination = &mut self.pagination;
+        if let Some(size) = env_parse("DEFAULT_PAGE_SIZE")? {
+            pagination.default_page_size = size;
+        }
+        if let Some(size) = env_parse("DEFAULT_CHAPTER_PAGE_SIZE")? {
+            pagination.default_chapter_page_size = size;
+        }
+        if let Some(size) = env_parse("MAX_PAGE_SIZE")? {
+            pagination.max_page_size = size;
+        }
+        if let Some(window) = env_parse("MAX_RESULT_WINDOW")? {
+            pagination.max_result_window = window;
+        }
+        if let Some(size) = env_parse("DEFAULT_SUGGEST_SIZE")? {
+            pagination.default_suggest_size = size;
+        }
+        if let Some(size) = env_parse("MAX_SUGGEST_SIZE")? {
+            pagination.max_suggest_size = size;
+        }
+
+        let search = &mut self.search;
+        if let Some(total) = env_parse("DID_YOU_MEAN_BELOW")? {
+            search.did_you_mean_below = total;
+        }
+        if let Some(size) = env_parse("DID_YOU_MEAN_SIZE")? {
+            search.did_you_mean_size = size;
+        }
+        if let Some(tag) = env_string("HIGHLIGHT_PRE_TAG") {
+            search.highlight_pre_tag = tag;
+        }
+        if let Some(tag) = env_string("HIGHLIGHT_POST_TAG") {
+            search.highlight_post_tag = tag;
+        }
+        if let Some(size) = env_parse("HIGHLIGHT_FRAGMENT_SIZE")? {
+            search.highlight_fragment_size = size;
+        }
+
+        let logging = &mut self.logging;
+        if let Some(level) = env_string("LOG_LEVEL") {
+            logging.level = level;
+        }
+        if let Some(format) = env_parse("LOG_FORMAT")? {
+            logging.format = format;
+        }
+
+        Ok(())
+    }
+
+    /// Rejects values that would only fail later, at request time.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+
+        let es = &self.elasticsearch;
+        if self.backend.kind == BackendKind::Elasticsearch {
+            if es.urls.is_empty() {
+                return invalid("elasticsearch.urls must name at least one node".to_string());
+            }
+            for url in &es.urls {
+                match reqwest::Url::parse(url) {
+                    Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+                    Ok(_) => return invalid(format!("elasticsearch.urls entry `{}` must use http or https", url)),
+                    Err(err) => return invalid(format!("elasticsearch.urls entry `{}` is not a valid URL: {}", url, err)),
+                }
+            }
+        }
+        if es.username.is_some() != es.password.is_some() {
+            return invalid("elasticsearch.username and elasticsearch.password must be set together".to_string());
+        }
+        if es.connect_timeout_ms == 0 || es.request_timeout_ms == 0 {
+            return invalid("elasticsearch timeouts must be greater than zero".to_string());
+        }
+        if es.health_check_interval_ms == 0 || (es.sniff && es.sniff_interval_ms == 0) {
+            return invalid("elasticsearch node check intervals must be greater than zero".to_string());
+        }
+        if es.retry.max_backoff_ms < es.retry.backoff_ms {
+            return invalid("elasticsearch.retry.max_backoff_ms must be at least backoff_ms".to_string());
+        }
+        if es.circuit_breaker.failure_threshold > 0 && es.circuit_breaker.open_ms == 0 {
+            return invalid("elasticsearch.circuit_breaker.open_ms must be greater than zero".to_string());
+        }
+        if !is_valid_index_name(&es.index_prefix) {
+            return invalid(format!("elasticsearch.index_prefix `{}` is not usable in an index name", es.index_prefix));
+        }
+        for (entity, index) in es.indices.entries() {
+            if index.is_empty() || !is_valid_index_name(index) {
+                return invalid(format!("elasticsearch.indices.{} `{}` is not a valid index name", entity, index));
+            }
+        }
+
+        let cors = &self.cors;
+        if let Some(origin) = cors.allowed_origins.iter().find(|origin| !is_valid_origin_pattern(origin)) {
+            return invalid(format!("cors.allowed_origins entry `{}` is not `*` or a scheme://host origin", origin));
+        }
+        if cors.allow_credentials && cors.allowed_origins.iter().any(|origin| origin == "*") {
+            return invalid("cors.allow_credentials cannot be combined with the `*` origin".to_string());
+        }
+        if let Some(method) = cors.allowed_methods.iter().find(|method| method.parse::<Method>().is_err()) {
+            return invalid(format!("cors.allowed_methods entry `{}` is not an HTTP method", method));
+        }
+        if let Some(header) = cors.allowed_headers.iter().find(|header| header.parse::<HeaderName>().is_err()) {
+            return invalid(format!("cors.allowed_headers entry `{}` is not a header name", header));
+        }
+
+        let compression = &self.compression;
+        for (name, level, range) in [
+            ("gzip_level", compression.gzip_level, 1..=9),
+            ("brotli_level", compression.brotli_level, 0..=11),
+            ("zstd_level", compression.zstd_level, 1..=22),
+        ] {
+            if !range.contains(&level) {
+                return invalid(format!(
+                    "compression.{} must be between {} and {}",
+                    name, range.start(), range.end()
+                ));
+            }
+        }
+
+        if self.cache.enabled && self.cache.max_entries == 0 {
+            return invalid("cache.max_entries must be greater than zero when the cache is enabled".to_string());
+        }
+
+        let pagination = &self.pagination;
+        if pagination.max_page_size == 0 {
+            return invalid("pagination.max_page_size must be greater than zero".to_string());
+        }
+        for (name, size) in [
+            ("default_page_size", pagination.default_page_size),
+            ("default_chapter_page_size", pagination.default_chapter_page_size),
+        ] {
+            if size == 0 || size > pagination.max_page_size {
+                return invalid(format!(
+                    "pagination.{} must be between 1 and max_page_size ({})",
+                    name, pagination.max_page_size
+                ));
+            }
+        }
+        if pagination.max_result_window < pagination.max_page_size {
+            return invalid("pagination.max_result_window must be at least max_page_size".to_string());
+        }
+        if pagination.default_suggest_size == 0 || pagination.default_suggest_size > pagination.max_suggest_size {
+            return invalid(format!(
+                "pagination.default_suggest_size must be between 1 and max_suggest_size ({})",
+                pagination.max_suggest_size
+            ));
+        }
+
+        let search = &self.search;
+        if search.highlight_pre_tag.is_empty() || search.highlight_post_tag.is_empty() {
+            return invalid("search.highlight_pre_tag and highlight_post_tag must not be empty".to_string());
+        }
+        if search.highlight_fragment_size == 0 {
+            return invalid("search.highlight_fragment_size must be at least 1".to_string());
+        }
+
+        if let Err(err) = tracing_subscriber::EnvFilter::try_new(&self.logging.level) {
+            return invalid(format!("logging.level `{}` is not a valid filter: {}", self.logging.level, err));
+        }
+
+        Ok(())
+    }
+}
+
+// Index names are lowercase and must not contain characters that Elasticsearch
+// treats as separators or wildcards in a request path.
+fn is_valid_index_name(name: &str) -> bool {
+    name.chars().all(|c| {
+        !c.is_uppercase() && !c.is_whitespace() && !matches!(c, '/' | '\\' | '*' | '?' | '"' | '<' | '>' | '|' | ',' | '#' | ':')
+    })
+}
+
+// `*`, or `scheme://host[:port]` where the host may start with `*.`.
+fn is_valid_origin_pattern(origin: &str) -> bool {
+    if origin == "*" {
+        return true;
+    }
+    let host = match origin.split_once("://") {
+        Some((scheme, host)) if !scheme.is_empty() => host,
+        _ => return false,
+    };
+    let host = host.strip_prefix("*.").unwrap_or(host);
+
+    !host.is_empty() && !host.contains(['/', '*', ' '])
+}
+
+// Unset and empty variables both count as "not configured".
+fn env_string(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn env_parse<T>(var: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match env_string(var) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|err| ConfigError::Env { var, message: format!("invalid value `{}`: {}", value, err) }),
+        None => Ok(None),
+    }
+}
+
+// `"http://a:9200"` or `["http://a:9200", "http://b:9200"]`
+fn string_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(value) => vec![value],
+        StringOrList::List(values) => values,
+    })
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
@@ -0,0 +1,26 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::backend::SearchBackend;
+use crate::config::Config;
+use crate::metrics::Metrics;
+use crate::response_cache::ResponseCache;
+
+/// State built once at startup and handed to every handler.
+pub struct AppContext {
+    pub config: Config,
+    pub backend: Arc<dyn SearchBackend>,
+    pub response_cache: ResponseCache,
+    pub metrics: Arc<Metrics>,
+    // Set once a shutdown signal arrives; `/readyz` fails from then on
+    pub shutting_down: AtomicBool,
+}
+
+impl AppContext {
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,151 @@
+use hyper::header::{
+    HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use crate::config::CorsConfig;
+use crate::error::AppError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OriginPattern {
+    Any,
+    Exact(String),
+    // `https://*.example.com` is stored as ("https://", ".example.com")
+    Subdomain { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    fn parse(pattern: &str) -> OriginPattern {
+        let pattern = pattern.trim().to_ascii_lowercase();
+        if pattern == "*" {
+            return OriginPattern::Any;
+        }
+        match pattern.split_once("://*.") {
+            Some((scheme, domain)) => OriginPattern::Subdomain {
+                scheme: format!("{}://", scheme),
+                suffix: format!(".{}", domain),
+            },
+            None => OriginPattern::Exact(pattern),
+        }
+    }
+
+    // `origin` is already lowercased
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Any => true,
+            OriginPattern::Exact(exact) => origin == exact,
+            OriginPattern::Subdomain { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| !subdomain.is_empty() && !subdomain.contains('/')),
+        }
+    }
+}
+
+/// Cross-origin policy built from [`CorsConfig`]. Allowed origins are echoed
+/// back rather than answered with `*`, so credentials work; responses must
+/// therefore carry `Vary: Origin`.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: Vec<OriginPattern>,
+    methods: Vec<Method>,
+    // Lowercase, for comparing with `Access-Control-Request-Headers`
+    headers: Vec<String>,
+    allow_credentials: bool,
+    max_age_secs: u64,
+}
+
+impl Cors {
+    /// Expects a validated config; entries that do not parse are skipped.
+    pub fn new(config: &CorsConfig) -> Cors {
+        Cors {
+            origins: config.allowed_origins.iter().map(|origin| OriginPattern::parse(origin)).collect(),
+            methods: config.allowed_methods.iter().filter_map(|method| method.parse().ok()).collect(),
+            headers: config
+                .allowed_headers
+                .iter()
+                .filter_map(|header| header.parse::<HeaderName>().ok())
+                .map(|header| header.as_str().to_string())
+                .collect(),
+            allow_credentials: config.allow_credentials,
+            max_age_secs: config.max_age_secs,
+        }
+    }
+
+    pub fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        let origin = match origin.to_str() {
+            Ok(origin) => origin.trim().to_ascii_lowercase(),
+            Err(_) => return false,
+        };
+        self.origins.iter().any(|pattern| pattern.matches(&origin))
+    }
+
+    /// Answers a preflight for a path served by `route_methods`. The origin,
+    /// the requested method and every requested header must be allowed,
+    /// otherwise the answer is a `403`.
+    pub fn preflight(&self, req: &Request<Body>, route_methods: &[Method]) -> Response<Body> {
+        let headers = req.headers();
+        let origin = match headers.get(ORIGIN) {
+            Some(origin) if self.allows_origin(origin) => origin.clone(),
+            _ => return AppError::forbidden("Origin not allowed").into_response(),
+        };
+
+        let methods: Vec<&Method> = self.methods.iter().filter(|method| route_methods.contains(method)).collect();
+        let requested_method = headers
+            .get(ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<Method>().ok());
+        if !requested_method.is_some_and(|requested| methods.contains(&&requested)) {
+            return AppError::forbidden("Method not allowed for cross-origin requests").into_response();
+        }
+
+        let requested_headers = headers
+            .get(ACCESS_CONTROL_REQUEST_HEADERS)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        let all_allowed = requested_headers
+            .split(',')
+            .map(|header| header.trim().to_ascii_lowercase())
+            .filter(|header| !header.is_empty())
+            .all(|header| self.headers.contains(&header));
+        if !all_allowed {
+            return AppError::forbidden("Header not allowed for cross-origin requests").into_response();
+        }
+
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        self.apply(origin, &mut response);
+
+        let allow_methods = methods.iter().map(|method| method.as_str()).collect::<Vec<_>>().join(", ");
+        let headers = response.headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&allow_methods) {
+            headers.insert(ACCESS_CONTROL_ALLOW_METHODS, value);
+        }
+        if let Ok(value) = HeaderValue::from_str(&self.headers.join(", ")) {
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, value);
+        }
+        headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(self.max_age_secs));
+        headers.append(VARY, HeaderValue::from_static("access-control-request-method"));
+        headers.append(VARY, HeaderValue::from_static("access-control-request-headers"));
+
+        response
+    }
+
+    /// Marks `response` as readable by the allowed `origin`.
+    pub fn apply(&self, origin: HeaderValue, response: &mut Response<Body>) {
+        let headers = response.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.allow_credentials {
+            headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+    }
+}
+
+/// A preflight is an `OPTIONS` request carrying both `Origin` and
+/// `Access-Control-Request-Method`; any other `OPTIONS` is routed as usual.
+pub fn is_preflight(req: &Request<Body>) -> bool {
+    req.method() == Method::OPTIONS
+        && req.headers().contains_key(ORIGIN)
+        && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
@@ -0,0 +1,150 @@
+use hyper::{Body, Response, StatusCode};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use serde_json::{json, Value};
+use std::fmt;
+use crate::backend::BackendError;
+use crate::validation::ValidationError;
+
+/// Stable, machine-readable error codes. Clients may switch on these, so
+/// never rename one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    BadRequest,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    UpstreamUnavailable,
+    UpstreamError,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "bad_request",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::MethodNotAllowed => "method_not_allowed",
+            ErrorCode::UpstreamUnavailable => "upstream_unavailable",
+            ErrorCode::UpstreamError => "upstream_error",
+            ErrorCode::Internal => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::UpstreamUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::UpstreamError => StatusCode::BAD_GATEWAY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// An error answered to the client. Renders into the same envelope as
+/// successful responses:
+///
+/// `{"message": "...", "error": true, "code": "not_found", "data": null}`
+///
+/// The message is meant for clients; internal details are logged when the
+/// error is created, never sent.
+#[derive(Debug)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub data: Value,
+    headers: HeaderMap,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError { code, message: message.into(), data: Value::Null, headers: HeaderMap::new() }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::BadRequest, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::Forbidden, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn method_not_allowed(allow: &str) -> Self {
+        AppError::new(ErrorCode::MethodNotAllowed, "Method Not Allowed")
+            .with_header(hyper::header::ALLOW, allow)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::Internal, message)
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    pub fn with_header(mut self, name: HeaderName, value: &str) -> Self {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.code.status()
+    }
+
+    pub fn into_response(self) -> Response<Body> {
+        let body = json!({
+            "message": self.message,
+            "error": true,
+            "code": self.code.as_str(),
+            "data": self.data
+        });
+
+        let mut response = Response::new(Body::from(body.to_string()));
+        *response.status_mut() = self.code.status();
+        *response.headers_mut() = self.headers;
+        response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        response
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code.as_str(), self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<ValidationError> for AppError {
+    fn from(err: ValidationError) -> Self {
+        AppError::bad_request(err.to_string()).with_data(json!({
+            "parameter": err.parameter,
+            "reason": err.reason
+        }))
+    }
+}
+
+impl From<BackendError> for AppError {
+    fn from(err: BackendError) -> Self {
+        tracing::error!(error = %err, "search backend error");
+
+        match err {
+            BackendError::Unavailable(_) => {
+                AppError::new(ErrorCode::UpstreamUnavailable, "Search service is unavailable")
+            }
+            BackendError::Status { .. } | BackendError::Invalid(_) => {
+                AppError::new(ErrorCode::UpstreamError, "Search service returned an error")
+            }
+        }
+    }
+}
@@ -0,0 +1,546 @@
+use hyper::StatusCode;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use rand::Rng;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::metrics::Metrics;
+use crate::node_pool::{Node, NodePool};
+use crate::config::{ElasticsearchConfig, RetryConfig};
+
+/// Thin wrapper around `reqwest::Client` that knows where Elasticsearch
+/// lives and how to authenticate. Requests are spread over the nodes of a
+/// [`NodePool`]; failed ones are retried, on the next node, per
+/// [`RetryConfig`] and guarded by a [`CircuitBreaker`]. Cheap to clone;
+/// clones share the same connection pool and breaker.
+#[derive(Clone)]
+pub struct EsClient {
+    http: Client,
+    nodes: Arc<NodePool>,
+    // (username, password) for basic auth
+    credentials: Option<(String, String)>,
+    retry: RetryConfig,
+    breaker: Arc<CircuitBreaker>,
+    metrics: Arc<Metrics>,
+}
+
+/// A single document returned by a search or mget.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub id: String,
+    pub source: Value,
+    // Field name to highlighted fragments, when the search asked for them
+    pub highlight: Option<BTreeMap<String, Vec<String>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub total: u64,
+    pub hits: Vec<Hit>,
+    // Options of every suggester in the request, best score first, each
+    // text once
+    pub suggestions: Vec<String>,
+}
+
+impl SearchResult {
+    pub fn into_sources(self) -> Vec<Value> {
+        self.hits.into_iter().map(|hit| hit.source).collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum EsError {
+    // The request never got a response (connection refused, timeout, ...)
+    Transport(reqwest::Error),
+    // Elasticsearch answered with a non-success status
+    Status { status: StatusCode, body: String },
+    // The response body was not what we expected
+    Decode(String),
+    // Not attempted: the circuit breaker is open after repeated failures
+    CircuitOpen,
+    // Not attempted: no node is configured
+    NoNodes,
+}
+
+impl fmt::Display for EsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EsError::Transport(err) => write!(f, "transport error: {}", err),
+            EsError::Status { status, body } => write!(f, "status {}: {}", status, body),
+            EsError::Decode(msg) => write!(f, "invalid response: {}", msg),
+            EsError::CircuitOpen => write!(f, "circuit breaker is open"),
+            EsError::NoNodes => write!(f, "no Elasticsearch node is configured"),
+        }
+    }
+}
+
+impl std::error::Error for EsError {}
+
+impl EsError {
+    // Worth another attempt: the request may succeed on a retry
+    fn is_retryable(&self) -> bool {
+        match self {
+            EsError::Transport(_) => true,
+            EsError::Status { status, .. } => matches!(
+                *status,
+                StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            EsError::Decode(_) | EsError::CircuitOpen | EsError::NoNodes => false,
+        }
+    }
+
+    // Counts against the circuit breaker: the cluster itself is unwell
+    fn is_upstream_failure(&self) -> bool {
+        match self {
+            EsError::Transport(_) => true,
+            EsError::Status { status, .. } => status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS,
+            EsError::Decode(_) | EsError::CircuitOpen | EsError::NoNodes => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for EsError {
+    fn from(err: reqwest::Error) -> Self {
+        EsError::Transport(err)
+    }
+}
+
+// Wire formats of the Elasticsearch responses we consume.
+#[derive(Deserialize)]
+struct RawSearchResponse {
+    hits: RawHits,
+    // Suggester name to one entry per analyzed chunk of its text
+    #[serde(default)]
+    suggest: BTreeMap<String, Vec<RawSuggestEntry>>,
+}
+
+#[derive(Deserialize)]
+struct RawHits {
+    #[serde(default)]
+    total: Option<RawTotal>,
+    #[serde(default)]
+    hits: Vec<RawHit>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTotal {
+    // Elasticsearch 7+
+    Object { value: u64 },
+    // Elasticsearch 6 and `rest_total_hits_as_int`
+    Number(u64),
+}
+
+#[derive(Deserialize)]
+struct RawSuggestEntry {
+    #[serde(default)]
+    options: Vec<RawSuggestOption>,
+}
+
+#[derive(Deserialize)]
+struct RawSuggestOption {
+    text: String,
+    #[serde(default)]
+    score: f64,
+}
+
+#[derive(Deserialize)]
+struct RawHit {
+    #[serde(rename = "_id", default)]
+    id: String,
+    #[serde(rename = "_source", default)]
+    source: Value,
+    #[serde(default)]
+    highlight: Option<BTreeMap<String, Vec<String>>>,
+}
+
+#[derive(Deserialize)]
+struct RawCountResponse {
+    count: u64,
+}
+
+#[derive(Deserialize)]
+struct RawMgetResponse {
+    docs: Vec<RawMgetDoc>,
+}
+
+#[derive(Deserialize)]
+struct RawMgetDoc {
+    #[serde(rename = "_id", default)]
+    id: String,
+    #[serde(default)]
+    found: bool,
+    #[serde(rename = "_source", default)]
+    source: Value,
+}
+
+#[derive(Deserialize)]
+struct RawMsearchResponse {
+    responses: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct RawNodesResponse {
+    #[serde(default)]
+    nodes: BTreeMap<String, RawNode>,
+}
+
+#[derive(Deserialize)]
+struct RawNode {
+    #[serde(default)]
+    http: Option<RawNodeHttp>,
+}
+
+#[derive(Deserialize)]
+struct RawNodeHttp {
+    #[serde(default)]
+    publish_address: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawClusterHealth {
+    status: String,
+}
+
+impl From<RawSearchResponse> for SearchResult {
+    fn from(raw: RawSearchResponse) -> Self {
+        let total = match raw.hits.total {
+            Some(RawTotal::Object { value }) | Some(RawTotal::Number(value)) => value,
+            None => 0,
+        };
+        let hits = raw.hits.hits
+            .into_iter()
+            .map(|hit| Hit { id: hit.id, source: hit.source, highlight: hit.highlight })
+            .collect();
+
+        let mut options: Vec<RawSuggestOption> = raw.suggest
+            .into_values()
+            .flatten()
+            .flat_map(|entry| entry.options)
+            .collect();
+        options.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        let mut suggestions: Vec<String> = Vec::new();
+        for option in options {
+            if !suggestions.contains(&option.text) {
+                suggestions.push(option.text);
+            }
+        }
+
+        SearchResult { total, hits, suggestions }
+    }
+}
+
+impl EsClient {
+    /// A client for the nodes at `urls`, using the default retry and
+    /// circuit breaker settings.
+    pub fn new(http: Client, urls: &[String], credentials: Option<(String, String)>) -> Self {
+        let defaults = ElasticsearchConfig::default();
+        let nodes = Arc::new(NodePool::new(urls));
+        let breaker = CircuitBreaker::new(
+            defaults.circuit_breaker.failure_threshold,
+            defaults.circuit_breaker.open_for(),
+        );
+
+        EsClient {
+            http,
+            nodes,
+            credentials,
+            retry: defaults.retry,
+            breaker: Arc::new(breaker),
+            metrics: Arc::new(Metrics::new()),
+        }
+    }
+
+    /// Records the latency of every call in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Builds a client with its own connection pool and the configured
+    /// timeouts, retries and circuit breaker.
+    pub fn from_config(config: &ElasticsearchConfig) -> Result<Self, EsError> {
+        let http = Client::builder()
+            .connect_timeout(config.connect_timeout())
+            .timeout(config.request_timeout())
+            .build()?;
+        let credentials = config.username.clone().zip(config.password.clone());
+        let breaker = CircuitBreaker::new(config.circuit_breaker.failure_threshold, config.circuit_breaker.open_for());
+
+        Ok(EsClient {
+            retry: config.retry.clone(),
+            breaker: Arc::new(breaker),
+            ..EsClient::new(http, &config.urls, credentials)
+        })
+    }
+
+    /// Runs `query` against `index/_search`.
+    pub async fn search(&self, index: &str, query: &Value) -> Result<SearchResult, EsError> {
+        tracing::debug!(index, query = %query, "Elasticsearch search");
+        let raw: RawSearchResponse = self.send(Method::POST, &format!("{}/_search", index), Target::index(index, "_search"), |req| req.json(query)).await?;
+
+        Ok(raw.into())
+    }
+
+    /// Returns the `_source` of the first document matching `query`.
+    pub async fn get_one(&self, index: &str, query: &Value) -> Result<Option<Value>, EsError> {
+        let mut query = query.clone();
+        query["size"] = json!(1);
+
+        let result = self.search(index, &query).await?;
+        Ok(result.hits.into_iter().next().map(|hit| hit.source))
+    }
+
+    /// Returns the `_source` of the first document whose `field` equals `value`.
+    pub async fn get_one_by_term(&self, index: &str, field: &str, value: &str) -> Result<Option<Value>, EsError> {
+        let query = json!({
+            "query": {
+                "term": { field: value }
+            }
+        });
+
+        self.get_one(index, &query).await
+    }
+
+    /// Counts the documents in `index` matching the query clause `query`.
+    pub async fn count(&self, index: &str, query: &Value) -> Result<u64, EsError> {
+        let body = json!({ "query": query });
+        let raw: RawCountResponse = self.send(Method::POST, &format!("{}/_count", index), Target::index(index, "_count"), |req| req.json(&body)).await?;
+
+        Ok(raw.count)
+    }
+
+    /// Fetches documents by `_id`, preserving the order of `ids` and
+    /// skipping the ones that do not exist.
+    pub async fn mget(&self, index: &str, ids: &[String]) -> Result<Vec<Hit>, EsError> {
+        let body = json!({ "ids": ids });
+        let raw: RawMgetResponse = self.send(Method::POST, &format!("{}/_mget", index), Target::index(index, "_mget"), |req| req.json(&body)).await?;
+
+        Ok(raw.docs
+            .into_iter()
+            .filter(|doc| doc.found)
+            .map(|doc| Hit { id: doc.id, source: doc.source, highlight: None })
+            .collect())
+    }
+
+    /// Runs several `(index, query)` searches in one round trip. Fails if
+    /// any of them fails.
+    pub async fn msearch(&self, searches: &[(&str, Value)]) -> Result<Vec<SearchResult>, EsError> {
+        let mut body = String::new();
+        for (index, query) in searches {
+            body.push_str(&json!({ "index": index }).to_string());
+            body.push('\n');
+            body.push_str(&query.to_string());
+            body.push('\n');
+        }
+
+        let indices = searches.iter().map(|(index, _)| *index).collect::<Vec<_>>().join(",");
+        let raw: RawMsearchResponse = self.send(Method::POST, "_msearch", Target::index(&indices, "_msearch"), |req| {
+            req.header("Content-Type", "application/x-ndjson").body(body.clone())
+        }).await?;
+
+        raw.responses
+            .into_iter()
+            .map(|response| {
+                if let Some(error) = response.get("error") {
+                    let status = response["status"].as_u64()
+                        .and_then(|s| StatusCode::from_u16(s as u16).ok())
+                        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                    return Err(EsError::Status { status, body: error.to_string() });
+                }
+                serde_json::from_value::<RawSearchResponse>(response)
+                    .map(SearchResult::from)
+                    .map_err(|err| EsError::Decode(err.to_string()))
+            })
+            .collect()
+    }
+
+    /// Lists the concrete indices `name` points to: the targets of an
+    /// alias, or the index itself. An unknown name resolves to nothing.
+    pub async fn resolve_index(&self, name: &str) -> Result<Vec<String>, EsError> {
+        match self.send::<BTreeMap<String, Value>, _>(Method::GET, &format!("{}/_alias", name), Target::index(name, "_alias"), |req| req).await {
+            Ok(indices) => Ok(indices.into_keys().collect()),
+            Err(EsError::Status { status, .. }) if status == StatusCode::NOT_FOUND => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The cluster's health status: `green`, `yellow` or `red`.
+    pub async fn cluster_health(&self) -> Result<String, EsError> {
+        let health: RawClusterHealth = self.send(Method::GET, "_cluster/health", Target::cluster("_cluster/health"), |req| req).await?;
+        Ok(health.status)
+    }
+
+    /// Checks every node that is marked down with `_cluster/health` and
+    /// marks the ones that answer as up again.
+    pub async fn check_nodes(&self) {
+        for node in self.nodes.nodes().into_iter().filter(|node| !node.is_alive()) {
+            let request = self.request_to(&node, Method::GET, "_cluster/health");
+            if let Ok(res) = request.send().await {
+                if res.status().is_success() {
+                    self.nodes.mark_up(&node);
+                }
+            }
+        }
+    }
+
+    /// Asks the cluster for its HTTP nodes (`_nodes/http`) and adds them to
+    /// the pool, next to the configured ones.
+    pub async fn sniff_nodes(&self) -> Result<usize, EsError> {
+        let raw: RawNodesResponse = self.send(Method::GET, "_nodes/http", Target::cluster("_nodes/http"), |req| req).await?;
+        // Sniffed addresses have no scheme; reuse the configured one
+        let scheme = self
+            .nodes
+            .nodes()
+            .first()
+            .and_then(|node| node.url().split_once("://").map(|(scheme, _)| scheme.to_string()))
+            .unwrap_or_else(|| "http".to_string());
+
+        let urls: Vec<String> = raw
+            .nodes
+            .into_values()
+            .filter_map(|node| node.http?.publish_address)
+            // `hostname/1.2.3.4:9200` when a host name is published
+            .map(|address| address.rsplit('/').next().unwrap_or(&address).to_string())
+            .map(|address| format!("{}://{}", scheme, address))
+            .collect();
+        let found = urls.len();
+        self.nodes.update(urls);
+
+        Ok(found)
+    }
+
+    /// Keeps the node list healthy in the background: re-checks down nodes
+    /// every `health_check_interval` and, when enabled, sniffs for new
+    /// nodes every `sniff_interval`. Must be called within a Tokio runtime.
+    pub fn spawn_maintenance(&self, config: &ElasticsearchConfig) {
+        let client = self.clone();
+        let interval = config.health_check_interval();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                client.check_nodes().await;
+            }
+        });
+
+        if config.sniff {
+            let client = self.clone();
+            let interval = config.sniff_interval();
+            tokio::spawn(async move {
+                loop {
+                    if let Err(err) = client.sniff_nodes().await {
+                        tracing::warn!(error = %err, "failed to sniff Elasticsearch nodes");
+                    }
+                    tokio::time::sleep(interval).await;
+                }
+            });
+        }
+    }
+
+    fn request_to(&self, node: &Node, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, format!("{}/{}", node.url(), path));
+        match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        }
+    }
+
+    // Sends `method path` with the body set by `build`, retrying with
+    // jittered exponential backoff while the error is retryable and
+    // attempts remain. Every attempt goes to the next node in the pool.
+    async fn send<T, F>(&self, method: Method, path: &str, target: Target<'_>, build: F) -> Result<T, EsError>
+    where
+        T: DeserializeOwned,
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        // One call, however many attempts, is one outcome for the breaker
+        let permit = self.breaker.try_acquire().ok_or(EsError::CircuitOpen)?;
+        let mut attempt = 0;
+        let result = loop {
+            match self.send_once(method.clone(), path, target, &build).await {
+                Err(err) if attempt < self.retry.max_retries && err.is_retryable() => {
+                    let max_backoff = self.retry.max_backoff(attempt);
+                    let backoff = rand::thread_rng().gen_range(Duration::ZERO..=max_backoff);
+                    tracing::warn!(error = %err, backoff_ms = backoff.as_millis() as u64, "Elasticsearch request failed, retrying");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+
+        let failed = result.as_ref().err().is_some_and(EsError::is_upstream_failure);
+        self.breaker.record(permit, !failed);
+        result
+    }
+
+    async fn send_once<T, F>(&self, method: Method, path: &str, target: Target<'_>, build: &F) -> Result<T, EsError>
+    where
+        T: DeserializeOwned,
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        let node = self.nodes.pick().ok_or(EsError::NoNodes)?;
+        let started = Instant::now();
+        let result = self.execute(build(self.request_to(&node, method, path))).await;
+        self.metrics.observe_es_request(target.index, target.endpoint, outcome(&result), started.elapsed());
+
+        if let Err(EsError::Transport(err)) = &result {
+            if err.is_connect() {
+                self.nodes.mark_down(&node);
+            }
+        }
+        result
+    }
+
+    async fn execute<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, EsError> {
+        let res = request.send().await?;
+        let status = res.status();
+        let bytes = res.bytes().await?;
+
+        if !status.is_success() {
+            let body = String::from_utf8_lossy(&bytes).into_owned();
+            return Err(EsError::Status { status, body });
+        }
+
+        serde_json::from_slice(&bytes).map_err(|err| EsError::Decode(err.to_string()))
+    }
+}
+
+// What a call's latency is recorded under: the index it reads, if any, and
+// the Elasticsearch endpoint.
+#[derive(Debug, Clone, Copy)]
+struct Target<'a> {
+    index: Option<&'a str>,
+    endpoint: &'static str,
+}
+
+impl<'a> Target<'a> {
+    fn index(index: &'a str, endpoint: &'static str) -> Self {
+        Target { index: Some(index), endpoint }
+    }
+
+    fn cluster(endpoint: &'static str) -> Self {
+        Target { index: None, endpoint }
+    }
+}
+
+// The `outcome` label of a call's latency.
+fn outcome<T>(result: &Result<T, EsError>) -> &'static str {
+    match result {
+        Ok(_) => "success",
+        Err(EsError::Status { status, .. }) if status.is_client_error() => "client_error",
+        Err(EsError::Status { .. }) => "server_error",
+        Err(EsError::Transport(err)) if err.is_timeout() => "timeout",
+        Err(EsError::Transport(_)) => "transport_error",
+        Err(EsError::Decode(_)) => "decode_error",
+        Err(EsError::CircuitOpen | EsError::NoNodes) => "not_attempted",
+    }
+}
@@ -0,0 +1,207 @@
+pub mod stories;
+pub mod chapters;
+pub mod categories;
+pub mod authors;
+pub mod search;
+pub mod monitoring;
+pub mod router;
+pub mod es_client;
+pub mod circuit_breaker;
+pub mod node_pool;
+pub mod backend;
+pub mod config;
+pub mod context;
+pub mod admin;
+pub mod validation;
+pub mod error;
+pub mod response;
+pub mod request_id;
+pub mod catch_panic;
+pub mod compression;
+pub mod cors;
+pub mod conditional;
+pub mod cache_policy;
+pub mod response_cache;
+pub mod stale_store;
+pub mod metrics;
+pub mod logging;
+pub mod shutdown;
+pub mod mappings;
+
+use hyper::{Body, Request, Response, Server};
+use hyper::service::{make_service_fn, service_fn};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::oneshot;
+use tracing::Instrument;
+use hyper::header::{HeaderValue, ACCEPT_ENCODING, ORIGIN, VARY};
+use backend::{ElasticsearchBackend, MemoryBackend, SearchBackend};
+use config::{BackendKind, Config};
+use catch_panic::catch_panic;
+use conditional::Preconditions;
+use context::AppContext;
+use error::AppError;
+use es_client::EsClient;
+use logging::AccessLog;
+use metrics::Metrics;
+use request_id::X_REQUEST_ID;
+use response_cache::{ResponseCache, X_CACHE};
+
+/// Builds the search backend selected by `config.backend.kind`. Calls to
+/// Elasticsearch are recorded in `metrics`.
+pub fn build_backend(config: &Config, metrics: &Arc<Metrics>) -> Result<Arc<dyn SearchBackend>, String> {
+    match config.backend.kind {
+        BackendKind::Elasticsearch => {
+            let es = EsClient::from_config(&config.elasticsearch)
+                .map_err(|err| format!("Failed to create Elasticsearch client: {}", err))?
+                .with_metrics(metrics.clone());
+            es.spawn_maintenance(&config.elasticsearch);
+            Ok(Arc::new(ElasticsearchBackend::new(es, config.elasticsearch.index_names())))
+        }
+        BackendKind::Memory => {
+            let backend = MemoryBackend::load(&config.backend.fixtures_dir)
+                .map_err(|err| format!("Failed to load fixtures: {}", err))?;
+            Ok(Arc::new(backend))
+        }
+    }
+}
+
+/// Binds the HTTP service to `config.server.listen_addr`. Returns the
+/// address actually bound (useful when asking for port 0) and the future
+/// running the server.
+pub fn serve(config: Config) -> Result<(SocketAddr, impl Future<Output = Result<(), hyper::Error>>), String> {
+    serve_with_shutdown(config, std::future::pending())
+}
+
+/// Like [`serve`], but shuts down gracefully once `shutdown` resolves:
+/// `/readyz` starts failing, new connections are still accepted for
+/// `shutdown_delay`, then the listener closes and the server future
+/// resolves when in-flight requests are done, or after `shutdown_timeout`.
+pub fn serve_with_shutdown(
+    config: Config,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(SocketAddr, impl Future<Output = Result<(), hyper::Error>>), String> {
+    let listen_addr = config.server.listen_addr;
+    let shutdown_delay = config.server.shutdown_delay();
+    let shutdown_timeout = config.server.shutdown_timeout();
+    let metrics = Arc::new(Metrics::new());
+    let backend = build_backend(&config, &metrics)?;
+    let response_cache = ResponseCache::new(&config.cache);
+    response_cache.spawn_maintenance();
+    let ctx = Arc::new(AppContext {
+        config,
+        backend,
+        response_cache,
+        metrics,
+        shutting_down: AtomicBool::new(false),
+    });
+    let draining_ctx = ctx.clone();
+    let service = Arc::new(Service {
+        router: router::Router::new(ctx.clone()),
+        cors: cors::Cors::new(&ctx.config.cors),
+        ctx,
+    });
+
+    let make_svc = make_service_fn(move |_conn| {
+        let service = service.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                handle_request(service.clone(), req)
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&listen_addr)
+        .map_err(|err| format!("Failed to bind {}: {}", listen_addr, err))?
+        .serve(make_svc);
+    let addr = server.local_addr();
+
+    let (draining_tx, draining_rx) = oneshot::channel();
+    let server = server.with_graceful_shutdown(async move {
+        shutdown.await;
+        draining_ctx.begin_shutdown();
+        tokio::time::sleep(shutdown_delay).await;
+        tracing::info!("no longer accepting connections, draining in-flight requests");
+        let _ = draining_tx.send(());
+    });
+
+    Ok((addr, shutdown::drain(server, draining_rx, shutdown_timeout)))
+}
+
+// Everything a request passes through, shared by all connections.
+struct Service {
+    ctx: Arc<AppContext>,
+    router: router::Router,
+    cors: cors::Cors,
+}
+
+async fn handle_request(service: Arc<Service>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let request_id = request_id::from_headers(req.headers());
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let metrics = &service.ctx.metrics;
+    let _in_flight = metrics.track_in_flight();
+    let started = Instant::now();
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let route = service.router.template_for(&path).unwrap_or(metrics::UNMATCHED_ROUTE);
+
+    let mut response = catch_panic(&request_id, process_request(service.clone(), req)).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(X_REQUEST_ID.clone(), value);
+    }
+
+    let status = response.status().as_u16();
+    let duration = started.elapsed();
+    metrics.observe_request(method.as_str(), route, status, duration);
+    if let Some(result) = response.headers().get(&X_CACHE).and_then(|value| value.to_str().ok()) {
+        metrics.record_cache_lookup(route, result);
+    }
+
+    let entry = AccessLog { request_id, method: method.to_string(), path, route: route.to_string(), status, duration };
+    Ok(logging::log_access(entry, response))
+}
+
+async fn process_request(service: Arc<Service>, req: Request<Body>) -> Response<Body> {
+    // Clone the headers before the request is moved
+    let accept_encoding = req.headers().get(ACCEPT_ENCODING).cloned();
+    let origin = req.headers().get(ORIGIN).cloned();
+    let preconditions = Preconditions::from_headers(req.headers());
+
+    let response = route_with_cors(&service, origin, req).await;
+    let mut response = preconditions.apply(response);
+    // The CORS headers depend on the origin, whether or not one was sent
+    response.headers_mut().append(VARY, HeaderValue::from_static("origin"));
+
+    compression::compress(response, accept_encoding.as_ref(), &service.ctx.config.compression, &service.ctx.metrics)
+}
+
+async fn route_with_cors(service: &Service, origin: Option<HeaderValue>, req: Request<Body>) -> Response<Body> {
+    // Preflights are only answered for paths that exist; others fall through
+    // to the router's 404
+    if cors::is_preflight(&req) {
+        let methods = service.router.methods_for(req.uri().path());
+        if !methods.is_empty() {
+            return service.cors.preflight(&req, &methods);
+        }
+    }
+
+    let origin = match origin {
+        Some(origin) if !service.cors.allows_origin(&origin) => {
+            return AppError::forbidden("Origin not allowed").into_response();
+        }
+        origin => origin,
+    };
+
+    let mut response = match service.router.route_request(req).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    };
+    if let Some(origin) = origin {
+        service.cors.apply(origin, &mut response);
+    }
+    response
+}
@@ -0,0 +1,93 @@
+use futures_util::TryStreamExt;
+use hyper::body::HttpBody;
+use hyper::{Body, Response};
+use std::time::Duration;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+use crate::config::{LogFormat, LoggingConfig};
+
+/// Installs the subscriber described by `config`, writing to stdout. Does
+/// nothing if one is already installed.
+pub fn init(config: &LoggingConfig) {
+    let _ = tracing::subscriber::set_global_default(subscriber(config, std::io::stdout));
+}
+
+/// The subscriber [`init`] installs, writing to `writer`. Events logged
+/// while a request is handled carry its span, and with it the request id.
+pub fn subscriber<W>(config: &LoggingConfig, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    // The level was validated with the rest of the config
+    let filter = EnvFilter::try_new(&config.level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(writer);
+
+    match config.format {
+        LogFormat::Json => Box::new(builder.json().with_current_span(true).with_span_list(false).finish()),
+        LogFormat::Text => Box::new(builder.finish()),
+    }
+}
+
+/// What the access log records about one request.
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    // The route template, so lines group by endpoint
+    pub route: String,
+    pub status: u16,
+    // Until the response headers were ready
+    pub duration: Duration,
+}
+
+impl AccessLog {
+    fn emit(&self, bytes: u64) {
+        tracing::info!(
+            target: "comic_es::access",
+            request_id = %self.request_id,
+            method = %self.method,
+            path = %self.path,
+            route = %self.route,
+            status = self.status,
+            duration_ms = self.duration.as_secs_f64() * 1000.0,
+            bytes,
+            "request completed"
+        );
+    }
+}
+
+/// Writes the access log line for `response`. A body of known length is
+/// logged right away; a streamed one (e.g. compressed) once it has been
+/// sent or dropped, with the bytes actually written.
+pub fn log_access(entry: AccessLog, response: Response<Body>) -> Response<Body> {
+    if let Some(bytes) = response.body().size_hint().exact() {
+        entry.emit(bytes);
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let mut pending = PendingLine { entry, bytes: 0 };
+    let body = body.inspect_ok(move |chunk| pending.add(chunk.len()));
+
+    Response::from_parts(parts, Body::wrap_stream(body))
+}
+
+// Logs its line when the streamed body is dropped, finished or not.
+struct PendingLine {
+    entry: AccessLog,
+    bytes: u64,
+}
+
+impl PendingLine {
+    fn add(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+    }
+}
+
+impl Drop for PendingLine {
+    fn drop(&mut self) {
+        self.entry.emit(self.bytes);
+    }
+}
@@ -0,0 +1,130 @@
+use serde_json::{json, Value};
+
+/// Analyzer that lowercases and strips diacritics, so `tien nghich`
+/// matches `Tiên Nghịch` (and `đ` matches `d`).
+pub const FOLDED_ANALYZER: &str = "vi_folded";
+
+/// Text fields a story search runs against, with their boosts. Each has a
+/// `.folded` sub-field, boosted lower so that a query typed with tone marks
+/// ranks exact-accent matches first.
+pub const STORY_SEARCH_FIELDS: [&str; 8] = [
+    "title^5",
+    "title.folded^4",
+    "alternate_titles^4",
+    "alternate_titles.folded^3",
+    "author.title^2",
+    "author.title.folded^1.5",
+    "description",
+    "description.folded^0.5",
+];
+
+/// `search_as_you_type` sub-fields of `title` a suggestion query runs
+/// against, in the stories, authors and categories indices.
+pub const SUGGEST_FIELDS: [&str; 3] = ["title.suggest", "title.suggest._2gram", "title.suggest._3gram"];
+
+/// Entities with settings and mappings defined here.
+pub const ENTITIES: [&str; 3] = ["stories", "authors", "categories"];
+
+/// Settings and mappings for a new index of `entity`, for `PUT /<index>`
+/// before reindexing into it and moving the alias.
+pub fn for_entity(entity: &str) -> Option<Value> {
+    match entity {
+        "stories" => Some(stories()),
+        "authors" => Some(authors()),
+        "categories" => Some(categories()),
+        _ => None,
+    }
+}
+
+pub fn stories() -> Value {
+    json!({
+        "settings": settings(),
+        "mappings": {
+            "properties": {
+                "story_id": { "type": "keyword" },
+                "title": title_text(),
+                "alternate_titles": searchable_text(false),
+                "url_key": { "type": "keyword" },
+                "description": searchable_text(false),
+                "author": {
+                    "properties": {
+                        "author_id": { "type": "keyword" },
+                        "title": searchable_text(false),
+                        "url_key": { "type": "keyword" }
+                    }
+                },
+                "categories": {
+                    "properties": {
+                        "category_id": { "type": "text", "fields": { "keyword": { "type": "keyword" } } },
+                        "title": { "type": "text" },
+                        "url_key": { "type": "keyword" }
+                    }
+                },
+                "is_full": { "type": "boolean" },
+                "status": { "type": "integer" },
+                "created_date": { "type": "date" },
+                "updated_date": { "type": "date" }
+            }
+        }
+    })
+}
+
+pub fn authors() -> Value {
+    json!({
+        "settings": settings(),
+        "mappings": {
+            "properties": {
+                "author_id": { "type": "keyword" },
+                "title": title_text(),
+                "url_key": { "type": "keyword" }
+            }
+        }
+    })
+}
+
+pub fn categories() -> Value {
+    json!({
+        "settings": settings(),
+        "mappings": {
+            "properties": {
+                "category_id": { "type": "keyword" },
+                "title": title_text(),
+                "url_key": { "type": "keyword" },
+                "type_category": { "type": "keyword" }
+            }
+        }
+    })
+}
+
+fn settings() -> Value {
+    json!({
+        "analysis": {
+            "analyzer": {
+                FOLDED_ANALYZER: {
+                    "type": "custom",
+                    "tokenizer": "standard",
+                    "filter": ["lowercase", "asciifolding"]
+                }
+            }
+        }
+    })
+}
+
+// Titles are searched, looked up exactly and completed as they are typed;
+// completion ignores diacritics too.
+fn title_text() -> Value {
+    let mut title = searchable_text(true);
+    title["fields"]["suggest"] = json!({ "type": "search_as_you_type", "analyzer": FOLDED_ANALYZER });
+    title
+}
+
+// A `text` field with a diacritic-free `.folded` sub-field and, when it is
+// used for exact lookups, a `.keyword` one.
+fn searchable_text(keyword: bool) -> Value {
+    let mut fields = json!({ "folded": { "type": "text", "analyzer": FOLDED_ANALYZER } });
+    if keyword {
+        fields["keyword"] = json!({ "type": "keyword", "ignore_above": 256 });
+    }
+
+    json!({ "type": "text", "fields": fields })
+}
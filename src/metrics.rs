@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::compression::Encoding;
+use crate::response_cache::CacheStats;
+
+/// `Content-Type` of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Label for requests that matched no route, so unknown paths cannot blow
+/// up the number of series.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+// Upper bounds of the latency buckets, in seconds; `+Inf` is implied
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    // Cumulative, one per bound in `LATENCY_BUCKETS`
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += secs;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        for (bucket, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, bucket);
+        }
+        let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, self.count);
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct CompressionBytes {
+    input: AtomicU64,
+    output: AtomicU64,
+}
+
+/// Counters and latency histograms since startup, rendered for Prometheus
+/// by `GET /metrics`. Labels are route templates, index names and fixed
+/// outcomes, never raw paths, so the number of series stays bounded.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    // (method, route, status)
+    requests: Mutex<BTreeMap<(&'static str, String, u16), Histogram>>,
+    // (index, endpoint, outcome); the index is empty for cluster-level calls
+    es_requests: Mutex<BTreeMap<(String, &'static str, &'static str), Histogram>>,
+    // (route, `X-Cache` result)
+    cache_lookups: Mutex<BTreeMap<(String, &'static str), u64>>,
+    in_flight: AtomicI64,
+    // Indexed like `Encoding::ALL`
+    compression: [CompressionBytes; 3],
+}
+
+/// Counts a request as in flight until dropped.
+pub struct InFlight<'a> {
+    metrics: &'a Metrics,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    pub fn track_in_flight(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight { metrics: self }
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        lock(&self.requests)
+            .entry((method_label(method), route.to_string(), status))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    /// `index` is the index a call reads, or several comma-separated for
+    /// `_msearch`, and `None` for cluster-level calls such as
+    /// `_cluster/health`. `endpoint` is the Elasticsearch API called.
+    pub fn observe_es_request(&self, index: Option<&str>, endpoint: &'static str, outcome: &'static str, elapsed: Duration) {
+        lock(&self.es_requests)
+            .entry((index.unwrap_or_default().to_string(), endpoint, outcome))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    /// Records the `X-Cache` value a route answered with. Unknown values are
+    /// ignored.
+    pub fn record_cache_lookup(&self, route: &str, result: &str) {
+        let result = match result {
+            "HIT" => "hit",
+            "MISS" => "miss",
+            "STALE" => "stale",
+            _ => return,
+        };
+        *lock(&self.cache_lookups).entry((route.to_string(), result)).or_default() += 1;
+    }
+
+    /// Bytes fed into the `encoding` compressor.
+    pub fn add_compression_input(&self, encoding: Encoding, bytes: usize) {
+        self.compression_bytes(encoding).input.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Bytes the `encoding` compressor produced.
+    pub fn add_compression_output(&self, encoding: Encoding, bytes: usize) {
+        self.compression_bytes(encoding).output.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Everything in the Prometheus text format. The response cache keeps
+    /// its own counters, so they are passed in.
+    pub fn render(&self, cache: &CacheStats) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "http_requests_total", "counter", "Requests answered, by route template and status.");
+        for ((method, route, status), histogram) in lock(&self.requests).iter() {
+            let labels = format!("method=\"{}\",route=\"{}\",status=\"{}\"", method, escape(route), status);
+            let _ = writeln!(out, "http_requests_total{{{}}} {}", labels, histogram.count);
+        }
+
+        header(&mut out, "http_request_duration_seconds", "histogram", "Time to answer a request, up to its headers.");
+        for ((method, route, status), histogram) in lock(&self.requests).iter() {
+            let labels = format!("method=\"{}\",route=\"{}\",status=\"{}\"", method, escape(route), status);
+            histogram.render(&mut out, "http_request_duration_seconds", &labels);
+        }
+
+        header(&mut out, "http_requests_in_flight", "gauge", "Requests being processed.");
+        let _ = writeln!(out, "http_requests_in_flight {}", self.in_flight.load(Ordering::Relaxed));
+
+        header(&mut out, "es_request_duration_seconds", "histogram", "Elasticsearch call latency, per attempt.");
+        for ((index, endpoint, outcome), histogram) in lock(&self.es_requests).iter() {
+            let labels = match index.as_str() {
+                "" => format!("endpoint=\"{}\",outcome=\"{}\"", endpoint, outcome),
+                index => format!("index=\"{}\",endpoint=\"{}\",outcome=\"{}\"", escape(index), endpoint, outcome),
+            };
+            histogram.render(&mut out, "es_request_duration_seconds", &labels);
+        }
+
+        header(&mut out, "response_cache_lookups_total", "counter", "Response cache results, by route template.");
+        for ((route, result), count) in lock(&self.cache_lookups).iter() {
+            let _ = writeln!(out, "response_cache_lookups_total{{route=\"{}\",result=\"{}\"}} {}", escape(route), result, count);
+        }
+
+        header(&mut out, "response_cache_hit_ratio", "gauge", "Share of cache lookups answered from the cache.");
+        let lookups = cache.hits + cache.misses;
+        let hit_ratio = if lookups == 0 { 0.0 } else { cache.hits as f64 / lookups as f64 };
+        let _ = writeln!(out, "response_cache_hit_ratio {}", hit_ratio);
+
+        header(&mut out, "response_cache_entries", "gauge", "Responses held by the cache, fresh or kept only to be served stale.");
+        let _ = writeln!(out, "response_cache_entries{{state=\"fresh\"}} {}", cache.entries);
+        let _ = writeln!(out, "response_cache_entries{{state=\"stale\"}} {}", cache.stale_entries);
+
+        header(&mut out, "compression_input_bytes_total", "counter", "Response bytes before compression.");
+        for encoding in Encoding::ALL {
+            let bytes = self.compression_bytes(encoding).input.load(Ordering::Relaxed);
+            let _ = writeln!(out, "compression_input_bytes_total{{encoding=\"{}\"}} {}", encoding.as_str(), bytes);
+        }
+
+        header(&mut out, "compression_output_bytes_total", "counter", "Response bytes after compression.");
+        for encoding in Encoding::ALL {
+            let bytes = self.compression_bytes(encoding).output.load(Ordering::Relaxed);
+            let _ = writeln!(out, "compression_output_bytes_total{{encoding=\"{}\"}} {}", encoding.as_str(), bytes);
+        }
+
+        out
+    }
+
+    fn compression_bytes(&self, encoding: Encoding) -> &CompressionBytes {
+        let index = Encoding::ALL.iter().position(|candidate| *candidate == encoding).unwrap_or(0);
+        &self.compression[index]
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Extension methods are any token a client cares to send, so they share
+// one label rather than each adding series.
+fn method_label(method: &str) -> &'static str {
+    match method {
+        "GET" => "GET",
+        "HEAD" => "HEAD",
+        "POST" => "POST",
+        "PUT" => "PUT",
+        "DELETE" => "DELETE",
+        "OPTIONS" => "OPTIONS",
+        "PATCH" => "PATCH",
+        _ => "other",
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Label values are route templates and index names, but a backslash or
+// quote must not break the format.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// A panic while holding the lock cannot leave a counter half-updated, so a
+// poisoned lock is still safe to use.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
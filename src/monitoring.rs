@@ -0,0 +1,110 @@
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Response};
+use serde_json::{json, Map, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::context::AppContext;
+use crate::error::{AppError, ErrorCode};
+use crate::metrics;
+use crate::response;
+
+pub fn fetch_cache_stats(ctx: Arc<AppContext>) -> Pin<Box<dyn Future<Output = Result<Response<Body>, AppError>> + Send>> {
+    Box::pin(async move {
+        let stats = ctx.response_cache.stats();
+        let lookups = stats.hits + stats.misses;
+        let hit_ratio = if lookups == 0 { 0.0 } else { stats.hits as f64 / lookups as f64 };
+
+        let response_body = json!({
+            "message": "Successfully",
+            "error": false,
+            "data": {
+                "hits": stats.hits,
+                "misses": stats.misses,
+                "coalesced": stats.coalesced,
+                "stale": stats.stale,
+                "entries": stats.entries,
+                "stale_entries": stats.stale_entries,
+                "hit_ratio": hit_ratio
+            }
+        });
+
+        Ok(response::json(response_body.to_string()))
+    })
+}
+
+/// Prometheus scrape endpoint.
+pub fn fetch_metrics(ctx: Arc<AppContext>) -> Pin<Box<dyn Future<Output = Result<Response<Body>, AppError>> + Send>> {
+    Box::pin(async move {
+        let body = ctx.metrics.render(&ctx.response_cache.stats());
+
+        let mut response = Response::new(Body::from(body));
+        response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(metrics::CONTENT_TYPE));
+        Ok(response)
+    })
+}
+
+/// Liveness: the process is up and serving requests. Never looks at the
+/// search backend, so a cluster outage does not get the service restarted.
+pub fn check_liveness(_ctx: Arc<AppContext>) -> Pin<Box<dyn Future<Output = Result<Response<Body>, AppError>> + Send>> {
+    Box::pin(async move {
+        let response_body = json!({
+            "message": "Successfully",
+            "error": false,
+            "data": { "status": "ok" }
+        });
+
+        Ok(response::json(response_body.to_string()))
+    })
+}
+
+/// Readiness: the service is not shutting down, the search backend is
+/// reachable, its cluster is not red and every index (or alias) exists.
+/// Answers 503 otherwise, with the same details.
+pub fn check_readiness(ctx: Arc<AppContext>) -> Pin<Box<dyn Future<Output = Result<Response<Body>, AppError>> + Send>> {
+    Box::pin(async move {
+        if ctx.is_shutting_down() {
+            return Err(AppError::new(ErrorCode::UpstreamUnavailable, "Service is shutting down")
+                .with_data(json!({ "status": "shutting_down" })));
+        }
+
+        let readiness = match ctx.backend.readiness().await {
+            Ok(readiness) => readiness,
+            Err(err) => {
+                tracing::warn!(error = %err, "readiness check failed");
+                return Err(not_ready(json!({
+                    "status": "not_ready",
+                    "backend": { "reachable": false }
+                })));
+            }
+        };
+
+        let indices: Map<String, Value> = readiness
+            .indices
+            .iter()
+            .map(|(entity, index, exists)| (entity.to_string(), json!({ "index": index, "exists": exists })))
+            .collect();
+        let ready = readiness.is_ready();
+        let data = json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "backend": { "reachable": true, "cluster_status": readiness.cluster_status },
+            "indices": indices
+        });
+
+        if !ready {
+            return Err(not_ready(data));
+        }
+
+        let response_body = json!({
+            "message": "Successfully",
+            "error": false,
+            "data": data
+        });
+
+        Ok(response::json(response_body.to_string()))
+    })
+}
+
+fn not_ready(data: Value) -> AppError {
+    AppError::new(ErrorCode::UpstreamUnavailable, "Service is not ready").with_data(data)
+}
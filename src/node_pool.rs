@@ -0,0 +1,103 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// One Elasticsearch HTTP endpoint, e.g. `http://es-1:9200`.
+#[derive(Debug)]
+pub struct Node {
+    url: String,
+    alive: AtomicBool,
+}
+
+impl Node {
+    fn new(url: String) -> Arc<Node> {
+        Arc::new(Node { url, alive: AtomicBool::new(true) })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Relaxed)
+    }
+}
+
+/// The nodes requests are spread over, round-robin. Nodes are marked down
+/// on connection errors and skipped until a health check marks them up
+/// again. If every node is down, all of them are tried anyway: failing
+/// there is no worse than failing here, and the circuit breaker takes over
+/// from that point.
+#[derive(Debug)]
+pub struct NodePool {
+    // The configured nodes; never dropped by sniffing
+    seeds: Vec<String>,
+    nodes: RwLock<Vec<Arc<Node>>>,
+    next: AtomicUsize,
+}
+
+impl NodePool {
+    pub fn new(urls: &[String]) -> NodePool {
+        let seeds: Vec<String> = urls.iter().map(|url| normalize(url)).collect();
+        let nodes = seeds.iter().cloned().map(Node::new).collect();
+
+        NodePool { seeds, nodes: RwLock::new(nodes), next: AtomicUsize::new(0) }
+    }
+
+    /// The node for the next request.
+    pub fn pick(&self) -> Option<Arc<Node>> {
+        let nodes = self.read();
+        let alive: Vec<&Arc<Node>> = nodes.iter().filter(|node| node.is_alive()).collect();
+        let candidates = if alive.is_empty() { nodes.iter().collect() } else { alive };
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % candidates.len();
+        Some(candidates[index].clone())
+    }
+
+    pub fn nodes(&self) -> Vec<Arc<Node>> {
+        self.read().clone()
+    }
+
+    pub fn mark_down(&self, node: &Node) {
+        if node.alive.swap(false, Ordering::Relaxed) {
+            tracing::warn!(node = %node.url, "Elasticsearch node marked down");
+        }
+    }
+
+    pub fn mark_up(&self, node: &Node) {
+        if !node.alive.swap(true, Ordering::Relaxed) {
+            tracing::info!(node = %node.url, "Elasticsearch node is back up");
+        }
+    }
+
+    /// Replaces the node list with the seeds plus `discovered`. Nodes that
+    /// were already known keep their state.
+    pub fn update(&self, discovered: Vec<String>) {
+        let mut urls = self.seeds.clone();
+        for url in discovered.iter().map(|url| normalize(url)) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+
+        let mut nodes = self.nodes.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let updated = urls
+            .into_iter()
+            .map(|url| match nodes.iter().find(|node| node.url == url) {
+                Some(node) => node.clone(),
+                None => Node::new(url),
+            })
+            .collect();
+        *nodes = updated;
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<Arc<Node>>> {
+        self.nodes.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn normalize(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
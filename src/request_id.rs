@@ -0,0 +1,25 @@
+use hyper::header::{HeaderMap, HeaderName};
+
+/// Header carrying the id that ties a response to the server's logs.
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The id a client or proxy sent in `X-Request-Id`, so one id follows the
+/// request across services; a new one when none was sent or it is not safe
+/// to log and echo back.
+pub fn from_headers(headers: &HeaderMap) -> String {
+    headers
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(str::to_string)
+        .unwrap_or_else(generate)
+}
+
+/// A new random request id, 32 lowercase hex characters.
+pub fn generate() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, NaiveDateTime};
+use hyper::{Body, Response};
+use hyper::header::{HeaderValue, CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+/// A `200 OK` response carrying an already serialized JSON body.
+pub fn json(body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+/// A `200 OK` response for a single document, with the validators clients
+/// need for conditional requests: a strong `ETag` hashed from the body and,
+/// when the document has a parseable `updated_date`, `Last-Modified`.
+pub fn document(source: &Value) -> Response<Body> {
+    let body = source.to_string();
+    let etag = strong_etag(body.as_bytes());
+    let last_modified = source["updated_date"].as_str().and_then(parse_date);
+
+    let mut response = json(body);
+    let headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(ETAG, etag);
+    }
+    if let Some(last_modified) = last_modified {
+        if let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(last_modified)) {
+            headers.insert(LAST_MODIFIED, value);
+        }
+    }
+    response
+}
+
+/// `"<hex>"`, the first 128 bits of the body's SHA-256.
+pub fn strong_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("\"{}\"", hex)
+}
+
+// Documents store RFC 3339 timestamps; older ones have no offset and are UTC.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.into());
+    }
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|date| date.and_utc().into())
+}
@@ -0,0 +1,346 @@
+use hyper::body::Bytes;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue, AGE, CACHE_CONTROL, WARNING};
+use hyper::{Body, Response, StatusCode};
+use lru::LruCache;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast;
+use crate::config::ResponseCacheConfig;
+use crate::error::AppError;
+use crate::stale_store::StaleStore;
+
+/// `HIT`, `MISS` or (for stale-if-error) `STALE`.
+pub static X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+
+/// A fully buffered response that can be replayed any number of times.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl CachedResponse {
+    pub fn new(status: StatusCode, headers: HeaderMap, body: Bytes) -> CachedResponse {
+        CachedResponse { status, headers, body }
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    pub async fn buffer(response: Response<Body>) -> Result<CachedResponse, hyper::Error> {
+        let (parts, body) = response.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+
+        Ok(CachedResponse { status: parts.status, headers: parts.headers, body })
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn to_response(&self) -> Response<Body> {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+/// Counters since startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    // Misses that waited for an identical request already in flight
+    pub coalesced: u64,
+    // Upstream failures answered with the last good response
+    pub stale: u64,
+    // Unexpired entries, the ones served as hits
+    pub entries: usize,
+    // Expired entries kept only to be served stale
+    pub stale_entries: usize,
+}
+
+struct Entry {
+    response: CachedResponse,
+    stored_at: Instant,
+    expires_at: Instant,
+}
+
+impl Entry {
+    fn is_fresh(&self, now: Instant) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// Bounded LRU cache of successful responses, each kept for its route's
+/// TTL. Concurrent misses for the same key are coalesced: one caller
+/// fetches, the others wait for its result.
+///
+/// With stale-if-error, expired entries are kept for `stale_max_age` and
+/// served when the upstream fails; a [`StaleStore`] on disk keeps them
+/// across restarts and LRU evictions.
+pub struct ResponseCache {
+    enabled: bool,
+    max_ttl: Duration,
+    // `None` when stale-if-error is off
+    stale_max_age: Option<Duration>,
+    stale_store: Option<StaleStore>,
+    entries: Mutex<LruCache<String, Entry>>,
+    inflight: Mutex<HashMap<String, broadcast::Sender<(CachedResponse, &'static str)>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+    stale: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(config: &ResponseCacheConfig) -> ResponseCache {
+        let capacity = NonZeroUsize::new(config.max_entries).unwrap_or(NonZeroUsize::MIN);
+        let stale_max_age = config.stale_if_error.then(|| Duration::from_secs(config.stale_max_age_secs));
+        let stale_store = match (&stale_max_age, &config.stale_dir) {
+            (Some(max_age), Some(dir)) => Some(StaleStore::new(dir.clone(), *max_age, config.stale_max_files)),
+            _ => None,
+        };
+
+        ResponseCache {
+            enabled: config.enabled && config.max_entries > 0,
+            max_ttl: Duration::from_secs(config.max_ttl_secs),
+            stale_max_age,
+            stale_store,
+            entries: Mutex::new(LruCache::new(capacity)),
+            inflight: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+            stale: AtomicU64::new(0),
+        }
+    }
+
+    /// Keeps the on-disk stale store, if any, within its bounds.
+    pub fn spawn_maintenance(&self) {
+        if let Some(store) = &self.stale_store {
+            store.spawn_sweeper();
+        }
+    }
+
+    /// A key that does not depend on parameter order or on empty
+    /// parameters, so `?a=1&b=` and `?a=1` share an entry.
+    pub fn key<'a>(
+        template: &str,
+        path_params: impl IntoIterator<Item = (&'a str, &'a str)>,
+        query_params: &HashMap<String, String>,
+    ) -> String {
+        let query: BTreeMap<&str, &str> = query_params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.trim()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+
+        let mut key = template.to_string();
+        for (name, value) in path_params {
+            key.push_str(&format!("|{}={}", name, urlencoding::encode(value)));
+        }
+        key.push('?');
+        for (name, value) in query {
+            key.push_str(&format!("{}={}&", urlencoding::encode(name), urlencoding::encode(value)));
+        }
+        key
+    }
+
+    /// Serves `key` from the cache, or runs `fetch` and caches a successful
+    /// result for `ttl` (capped by the configured maximum). Responses carry
+    /// `X-Cache: HIT` or `MISS`, or `STALE` when the upstream failed and
+    /// the last good response was served instead.
+    pub async fn get_or_fetch<F, Fut>(&self, key: String, ttl: Duration, fetch: F) -> Response<Body>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Response<Body>>,
+    {
+        if !self.enabled {
+            return fetch().await;
+        }
+        if let Some(response) = self.lookup(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return with_x_cache(response.to_response(), "HIT");
+        }
+
+        let flight = {
+            let mut inflight = lock(&self.inflight);
+            // The leader may have finished between the lookup and the lock
+            if let Some(response) = self.lookup(&key) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return with_x_cache(response.to_response(), "HIT");
+            }
+            match inflight.get(&key) {
+                Some(sender) => Err(sender.subscribe()),
+                None => {
+                    let (sender, _) = broadcast::channel(1);
+                    inflight.insert(key.clone(), sender);
+                    Ok(Flight { cache: self, key: Some(key.clone()) })
+                }
+            }
+        };
+        // Only now is this a miss, whether this caller leads or waits
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        match flight {
+            Ok(flight) => {
+                let response = match CachedResponse::buffer(fetch().await).await {
+                    Ok(response) => response,
+                    Err(err) => {
+                        tracing::error!(key = %key, error = %err, "failed to buffer response");
+                        return AppError::internal("Internal server error").into_response();
+                    }
+                };
+
+                let (response, label) = if response.status().is_success() {
+                    self.store(&key, response.clone(), ttl);
+                    (response, "MISS")
+                } else if is_upstream_failure(response.status()) {
+                    match self.last_good(&key).await {
+                        Some(stale) => {
+                            self.stale.fetch_add(1, Ordering::Relaxed);
+                            (stale, "STALE")
+                        }
+                        None => (response, "MISS"),
+                    }
+                } else {
+                    (response, "MISS")
+                };
+                flight.land(response.clone(), label);
+                with_x_cache(response.to_response(), label)
+            }
+            Err(mut receiver) => match receiver.recv().await {
+                Ok((response, label)) => {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    with_x_cache(response.to_response(), label)
+                }
+                // The leader was cancelled or panicked; fetch on our own
+                Err(_) => with_x_cache(fetch().await, "MISS"),
+            },
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let now = Instant::now();
+        let (entries, stale_entries) = lock(&self.entries)
+            .iter()
+            .fold((0, 0), |(fresh, stale), (_, entry)| {
+                if entry.is_fresh(now) { (fresh + 1, stale) } else { (fresh, stale + 1) }
+            });
+
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+            entries,
+            stale_entries,
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Option<CachedResponse> {
+        let now = Instant::now();
+        let mut entries = lock(&self.entries);
+        match entries.get(key) {
+            // The replayed `max-age` counts from when the entry was stored,
+            // so downstream caches are told how much of it has passed
+            Some(entry) if entry.is_fresh(now) => {
+                let mut response = entry.response.clone();
+                let age = now.duration_since(entry.stored_at).as_secs();
+                response.headers.insert(AGE, HeaderValue::from(age));
+                Some(response)
+            }
+            // Expired entries stay around as long as they may be served stale
+            Some(entry) if self.stale_max_age.is_some_and(|max_age| now < entry.expires_at + max_age) => None,
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn store(&self, key: &str, response: CachedResponse, ttl: Duration) {
+        let ttl = ttl.min(self.max_ttl);
+        if let Some(store) = &self.stale_store {
+            store.save(key, &response, SystemTime::now() + ttl);
+        }
+        let now = Instant::now();
+        lock(&self.entries).put(key.to_string(), Entry { response, stored_at: now, expires_at: now + ttl });
+    }
+
+    // The last successful response for `key`, if stale-if-error is on and
+    // it expired less than `stale_max_age` ago. Marked so that neither
+    // clients nor the CDN keep it.
+    async fn last_good(&self, key: &str) -> Option<CachedResponse> {
+        let max_age = self.stale_max_age?;
+
+        let in_memory = lock(&self.entries)
+            .peek(key)
+            .filter(|entry| Instant::now() < entry.expires_at + max_age)
+            .map(|entry| entry.response.clone());
+        let mut response = match (in_memory, &self.stale_store) {
+            (Some(response), _) => response,
+            (None, Some(store)) => store.load(key).await?,
+            (None, None) => return None,
+        };
+
+        response.headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        response.headers.insert(WARNING, HeaderValue::from_static("110 - \"Response is Stale\""));
+        Some(response)
+    }
+}
+
+// Failures of the search service, as opposed to the client's own mistakes.
+fn is_upstream_failure(status: StatusCode) -> bool {
+    matches!(status, StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT)
+}
+
+// The leader's claim on a key. Dropping it without landing (the request was
+// cancelled or panicked) releases the waiters so they can fetch themselves.
+struct Flight<'a> {
+    cache: &'a ResponseCache,
+    // Taken once landed, so the drop does not remove a later leader's claim
+    key: Option<String>,
+}
+
+impl Flight<'_> {
+    fn land(mut self, response: CachedResponse, label: &'static str) {
+        let key = self.key.take();
+        if let Some(sender) = key.and_then(|key| lock(&self.cache.inflight).remove(&key)) {
+            // No receivers is fine: nobody was waiting
+            let _ = sender.send((response, label));
+        }
+    }
+}
+
+impl Drop for Flight<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            lock(&self.cache.inflight).remove(&key);
+        }
+    }
+}
+
+fn with_x_cache(mut response: Response<Body>, status: &'static str) -> Response<Body> {
+    response.headers_mut().insert(X_CACHE.clone(), HeaderValue::from_static(status));
+    response
+}
+
+// A panic while holding the lock cannot leave the map half-updated, so a
+// poisoned lock is still safe to use.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -1,145 +1,421 @@
-use hyper::{Body, Request, Response};
-use reqwest::Client;
+use hyper::{Body, Method, Request, Response};
+use hyper::header::{HeaderValue, CACHE_CONTROL};
 use std::collections::HashMap;
 use std::convert::Infallible;
+use std::ops::Index;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::future::Future;
+use urlencoding::decode;
 use crate::stories;
 use crate::chapters;
 use crate::categories;
 use crate::authors;
+use crate::search;
+use crate::monitoring;
+use crate::backend::{DidYouMean, Highlight, StoryQuery};
+use crate::cache_policy::{CachePolicy, DAY, HOUR, MINUTE};
+use crate::context::AppContext;
+use crate::response_cache::ResponseCache;
+use crate::error::AppError;
+use crate::validation::{self, ValidationError};
 
-type ResponseFuture = Pin<Box<dyn Future<Output = Result<Response<Body>, Infallible>> + Send>>;
+// Search results change whenever a chapter is published, so lists are kept
+// short and refreshed in the background. Published chapters rarely change.
+const STORY_LIST: CachePolicy = CachePolicy::public(MINUTE)
+    .s_maxage(MINUTE)
+    .stale_while_revalidate(5 * MINUTE)
+    .surrogate_key("stories");
+const STORY_DETAIL: CachePolicy = CachePolicy::public(5 * MINUTE).s_maxage(10 * MINUTE).stale_while_revalidate(HOUR);
+const CHAPTER_LIST: CachePolicy = CachePolicy::public(5 * MINUTE).s_maxage(5 * MINUTE).stale_while_revalidate(HOUR);
+const CHAPTER_DETAIL: CachePolicy = CachePolicy::public(DAY).s_maxage(DAY);
+const CATEGORIES: CachePolicy = CachePolicy::public(HOUR).s_maxage(HOUR).surrogate_key("categories");
+const AUTHORS: CachePolicy = CachePolicy::public(HOUR).s_maxage(HOUR).surrogate_key("authors");
+// Asked on every keystroke; the same prefixes come up again and again
+const SUGGEST: CachePolicy = CachePolicy::public(5 * MINUTE).s_maxage(5 * MINUTE).surrogate_key("stories");
 
-type Handler = Box<dyn Fn(Client, Vec<String>, HashMap<String, String>) -> ResponseFuture + Send + Sync>;
+type ResponseFuture = Pin<Box<dyn Future<Output = Result<Response<Body>, AppError>> + Send>>;
+
+type Handler = Box<dyn Fn(Arc<AppContext>, PathParams, HashMap<String, String>) -> ResponseFuture + Send + Sync>;
+
+/// Percent-decoded values captured from `{name}` segments of a route template.
+#[derive(Debug, Default, Clone)]
+pub struct PathParams {
+    params: Vec<(String, String)>,
+}
+
+impl PathParams {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl Index<&str> for PathParams {
+    type Output = str;
+
+    /// Panics if the route template does not declare `{name}`.
+    fn index(&self, name: &str) -> &str {
+        self.get(name)
+            .unwrap_or_else(|| panic!("route template has no `{{{}}}` parameter", name))
+    }
+}
+
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+struct Route {
+    method: Method,
+    template: String,
+    segments: Vec<Segment>,
+    handler: Handler,
+    cache: Option<CachePolicy>,
+}
+
+enum RouteMatch {
+    Matched(PathParams),
+    // The path has the right shape but a parameter is not valid percent-encoded UTF-8
+    BadParam(String),
+}
+
+impl Route {
+    fn new(method: Method, template: &str, handler: Handler) -> Self {
+        let segments = split_path(template)
+            .map(|segment| {
+                match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => Segment::Param(name.to_string()),
+                    None => Segment::Literal(segment.to_string()),
+                }
+            })
+            .collect();
+
+        Route { method, template: template.to_string(), segments, handler, cache: None }
+    }
+
+    /// Sets the caching headers for the route's successful responses.
+    /// Routes without a policy are not cacheable.
+    pub fn cache(&mut self, policy: CachePolicy) -> &mut Self {
+        self.cache = Some(policy);
+        self
+    }
+
+    fn match_path(&self, parts: &[&str]) -> Option<RouteMatch> {
+        if parts.len() != self.segments.len() {
+            return None;
+        }
+
+        let mut params = Vec::new();
+        for (segment, part) in self.segments.iter().zip(parts) {
+            match segment {
+                Segment::Literal(literal) => {
+                    if literal != part {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    if part.is_empty() {
+                        return None;
+                    }
+                    match decode(part) {
+                        Ok(value) => params.push((name.clone(), value.into_owned())),
+                        Err(_) => return Some(RouteMatch::BadParam(name.clone())),
+                    }
+                }
+            }
+        }
+
+        Some(RouteMatch::Matched(PathParams { params }))
+    }
+}
 
 pub struct Router {
-    routes: HashMap<String, Handler>,
+    routes: Vec<Route>,
+    ctx: Arc<AppContext>,
 }
 
 impl Router {
-    pub fn new() -> Self {
-        let mut routes: HashMap<String, Handler> = HashMap::new();
+    pub fn new(ctx: Arc<AppContext>) -> Self {
+        let mut router = Router { routes: Vec::new(), ctx };
 
         // STORIES ROUTERS
         // Route for fetching stories
-        routes.insert("/stories/list".to_string(), Box::new(move |client, _path_parts, query_params| {
-            stories::fetch_stories(client, query_params)
-        }));
+        router.get("/stories/list", Box::new(move |ctx, _params, query_params| {
+            validated(|| {
+                let limits = &ctx.config.pagination;
+                let pagination = validation::pagination(&query_params, limits.default_page_size, limits)?;
+                let query = StoryQuery {
+                    text: validation::optional_text(&query_params, "title")?,
+                    author_id: validation::optional_id(&query_params, "author_id")?,
+                    category_id: None,
+                    is_full: validation::flag(&query_params, "is_full")?,
+                    sort_by_latest: validation::flag(&query_params, "sort_by_latest")?,
+                    page: pagination.page,
+                    size: pagination.size,
+                    did_you_mean: DidYouMean::new(&ctx.config.search),
+                    highlight: validation::flag(&query_params, "highlight")?.then(|| Highlight::new(&ctx.config.search)),
+                };
+
+                Ok(stories::fetch_stories(ctx, query))
+            })
+        }))
+        .cache(STORY_LIST);
 
         // Route for fetching stories by category
-        routes.insert("/stories/list_by_category".to_string(), Box::new(move |client, path_parts, query_params| {
-            if path_parts.len() < 4 {
-                return Box::pin(async {
-                    Ok(Response::builder()
-                        .status(400)
-                        .body(Body::from("Bad Request: Missing category ID"))
-                        .unwrap())
-                });
-            }
-            let category_id = path_parts[3].clone();
-            let page = query_params.get("page").and_then(|p| p.parse::<usize>().ok()).unwrap_or(1);
-            let size = query_params.get("size").and_then(|s| s.parse::<usize>().ok()).unwrap_or(10);
-            let sort_by_latest = query_params.get("sort_by_latest").map_or(false, |v| v == "true");
+        router.get("/stories/list_by_category/{category_id}", Box::new(move |ctx, params, query_params| {
+            validated(|| {
+                let category_id = validation::id("category_id", &params["category_id"])?;
+                let limits = &ctx.config.pagination;
+                let pagination = validation::pagination(&query_params, limits.default_page_size, limits)?;
+                let sort_by_latest = validation::flag(&query_params, "sort_by_latest")?;
 
-            stories::fetch_stories_by_category(client, category_id, page, size, sort_by_latest)
-        }));
+                Ok(stories::fetch_stories_by_category(ctx, category_id, pagination, sort_by_latest))
+            })
+        }))
+        .cache(STORY_LIST);
 
-        // Add other routes like fetch_story_detail, fetch_chapter_detail, etc.
-        routes.insert("/stories/detail_by_url_key".to_string(), Box::new(move |client, path_parts, _| {
-            stories::fetch_story_detail(client, path_parts)
-        }));
+        router.get("/stories/detail_by_url_key/{url_key}", Box::new(move |ctx, params, _| {
+            validated(|| {
+                let url_key = validation::id("url_key", &params["url_key"])?;
+                Ok(stories::fetch_story_detail(ctx, url_key))
+            })
+        }))
+        .cache(STORY_DETAIL);
 
         // CHAPTERS ROUTERS
-        routes.insert("/chapters/list".to_string(), Box::new(move |client, path_parts, query_params| {
-            if path_parts.len() < 4 {
-                return Box::pin(async {
-                    Ok(Response::builder()
-                        .status(400)
-                        .body(Body::from("Bad Request: Missing story ID"))
-                        .unwrap())
-                });
-            }
-        
-            let story_id = path_parts[3].clone();
-            let page = query_params.get("page").and_then(|p| p.parse::<usize>().ok()).unwrap_or(1);
-            let size = query_params.get("size").and_then(|s| s.parse::<usize>().ok()).unwrap_or(50);
-        
-            chapters::fetch_chapters_by_story_id(client, story_id, page, size)
-        }));
+        router.get("/chapters/list/{story_id}", Box::new(move |ctx, params, query_params| {
+            validated(|| {
+                let story_id = validation::id("story_id", &params["story_id"])?;
+                let limits = &ctx.config.pagination;
+                let pagination = validation::pagination(&query_params, limits.default_chapter_page_size, limits)?;
 
-        routes.insert("/chapters/detail_by_url".to_string(), Box::new(move |client, path_parts, _| {
-            chapters::fetch_chapter_detail(client, path_parts)
-        }));
+                Ok(chapters::fetch_chapters_by_story_id(ctx, story_id, pagination))
+            })
+        }))
+        .cache(CHAPTER_LIST);
+
+        router.get("/chapters/detail_by_url/{story_key}/{chapter_key}", Box::new(move |ctx, params, _| {
+            validated(|| {
+                let story_key = validation::id("story_key", &params["story_key"])?;
+                let chapter_key = validation::id("chapter_key", &params["chapter_key"])?;
+
+                Ok(chapters::fetch_chapter_detail(ctx, story_key, chapter_key))
+            })
+        }))
+        .cache(CHAPTER_DETAIL);
 
         // CATEGORIES ROUTERS
-        routes.insert("/categories/list".to_string(), Box::new(move |client, _path_parts, query_params| {
-            categories::fetch_categories(client, query_params)
-        }));
+        router.get("/categories/list", Box::new(move |ctx, _params, query_params| {
+            validated(|| {
+                let type_category = validation::optional_id(&query_params, "type_category")?;
+                Ok(categories::fetch_categories(ctx, type_category))
+            })
+        }))
+        .cache(CATEGORIES);
 
-        routes.insert("/categories/detail_by_url_key".to_string(), Box::new(move |client, path_parts, _query_params| {
-            if path_parts.len() < 4 {
-                return Box::pin(async {
-                    Ok(Response::builder()
-                        .status(400)
-                        .body(Body::from("Bad Request: Missing URL key"))
-                        .unwrap())
-                });
-            }
-        
-            let url_key = path_parts[3].clone(); // Extract the URL key from the path
-            categories::fetch_category_detail_by_url_key(client, url_key)
-        }));
+        router.get("/categories/detail_by_url_key/{url_key}", Box::new(move |ctx, params, _query_params| {
+            validated(|| {
+                let url_key = validation::id("url_key", &params["url_key"])?;
+                Ok(categories::fetch_category_detail_by_url_key(ctx, url_key))
+            })
+        }))
+        .cache(CATEGORIES);
 
         // AUTHORS ROUTERS
         // Route for fetching authors by URL key
-        routes.insert("/authors/detail_by_url_key".to_string(), Box::new(move |client, path_parts, _query_params| {
-            if path_parts.len() < 4 {
-                return Box::pin(async {
-                    Ok(Response::builder()
-                        .status(400)
-                        .body(Body::from("Bad Request: Missing URL key"))
-                        .unwrap())
-                });
-            }
-        
-            let url_key = path_parts[3].clone(); // Extract the URL key from the path
-            authors::fetch_author_detail_by_url_key(client, url_key)
+        router.get("/authors/detail_by_url_key/{url_key}", Box::new(move |ctx, params, _query_params| {
+            validated(|| {
+                let url_key = validation::id("url_key", &params["url_key"])?;
+                Ok(authors::fetch_author_detail_by_url_key(ctx, url_key))
+            })
+        }))
+        .cache(AUTHORS);
+
+        // SEARCH ROUTERS
+        // Route for completing what a reader types in the search box
+        router.get("/search/suggest", Box::new(move |ctx, _params, query_params| {
+            validated(|| {
+                let text = validation::text(&query_params, "q")?;
+                let limits = &ctx.config.pagination;
+                let size = validation::limit(&query_params, "size", limits.default_suggest_size, limits.max_suggest_size)?;
+
+                Ok(search::fetch_suggestions(ctx, text, size))
+            })
+        }))
+        .cache(SUGGEST);
+
+        // MONITORING ROUTERS
+        router.get("/cache/stats", Box::new(move |ctx, _params, _query_params| {
+            monitoring::fetch_cache_stats(ctx)
+        }));
+
+        router.get("/metrics", Box::new(move |ctx, _params, _query_params| {
+            monitoring::fetch_metrics(ctx)
+        }));
+
+        router.get("/healthz", Box::new(move |ctx, _params, _query_params| {
+            monitoring::check_liveness(ctx)
         }));
-        
 
-        Router { routes }
+        router.get("/readyz", Box::new(move |ctx, _params, _query_params| {
+            monitoring::check_readiness(ctx)
+        }));
+
+        router
+    }
+
+    /// Registers a handler for `GET` requests matching `template`, where
+    /// `{name}` segments capture a path parameter.
+    fn get(&mut self, template: &str, handler: Handler) -> &mut Route {
+        self.add(Method::GET, template, handler)
+    }
+
+    fn add(&mut self, method: Method, template: &str, handler: Handler) -> &mut Route {
+        self.routes.push(Route::new(method, template, handler));
+        let last = self.routes.len() - 1;
+        &mut self.routes[last]
     }
 
     pub async fn route_request(
         &self,
-        client: &Client,
         req: Request<Body>
     ) -> Result<Response<Body>, Infallible> {
-        let path = req.uri().path().to_string();
-        let parts: Vec<String> = path.split('/').map(|s| s.to_string()).collect();
-        let query_params: HashMap<String, String> = req.uri().query()
-            .map(|query| {
-                query.split('&')
-                    .map(|pair| {
-                        let mut iter = pair.split('=');
-                        (iter.next().unwrap_or("").to_string(), iter.next().unwrap_or("").to_string())
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
+        match self.dispatch(req).await {
+            Ok(response) => Ok(response),
+            Err(err) => Ok(no_store(err.into_response())),
+        }
+    }
 
-        // Match the path and call the corresponding handler
-        for (route_path, handler) in &self.routes {
-            if path.starts_with(route_path) {
-                return handler(client.clone(), parts, query_params).await;
+    /// Template of the first route matching `path`, whatever its method.
+    pub fn template_for(&self, path: &str) -> Option<&str> {
+        let parts: Vec<&str> = split_path(path).collect();
+        self.routes
+            .iter()
+            .find(|route| route.match_path(&parts).is_some())
+            .map(|route| route.template.as_str())
+    }
+
+    /// Methods registered for `path`, in registration order. Empty when no
+    /// route matches the path at all.
+    pub fn methods_for(&self, path: &str) -> Vec<Method> {
+        let parts: Vec<&str> = split_path(path).collect();
+        let mut methods: Vec<Method> = Vec::new();
+        for route in &self.routes {
+            if route.match_path(&parts).is_some() && !methods.contains(&route.method) {
+                methods.push(route.method.clone());
             }
         }
+        methods
+    }
+
+    async fn dispatch(&self, req: Request<Body>) -> Result<Response<Body>, AppError> {
+        let parts: Vec<&str> = split_path(req.uri().path()).collect();
+        let query_params = parse_query(req.uri().query());
+
+        // Routes are tried in registration order; the first one matching both
+        // path and method wins.
+        let mut allowed: Vec<&Method> = Vec::new();
+        for route in &self.routes {
+            match route.match_path(&parts) {
+                None => continue,
+                Some(RouteMatch::BadParam(name)) => {
+                    return Err(ValidationError::new(&name, "is not valid percent-encoded UTF-8").into());
+                }
+                Some(RouteMatch::Matched(params)) => {
+                    if route.method == req.method() {
+                        return Ok(self.call(route, params, query_params).await);
+                    }
+                    if !allowed.contains(&&route.method) {
+                        allowed.push(&route.method);
+                    }
+                }
+            }
+        }
+
+        if !allowed.is_empty() {
+            let allow = allowed.iter().map(|m| m.as_str()).collect::<Vec<_>>().join(", ");
+            return Err(AppError::method_not_allowed(&allow));
+        }
 
         // Default response for unknown routes
-        Ok(Response::builder()
-            .status(404)
-            .body(Body::from("Not Found"))
-            .unwrap())
+        Err(AppError::not_found("Not Found"))
+    }
+
+    // Runs the route's handler and stamps its caching headers. Routes with a
+    // cache policy are served through the response cache.
+    async fn call(&self, route: &Route, params: PathParams, query_params: HashMap<String, String>) -> Response<Body> {
+        let policy = match route.cache {
+            Some(policy) => policy,
+            None => return no_store(self.run(route, params, query_params).await),
+        };
+
+        let key = ResponseCache::key(&route.template, params.iter(), &query_params);
+        self.ctx.response_cache.get_or_fetch(key, policy.ttl(), || async move {
+            let mut response = self.run(route, params, query_params).await;
+            if !response.status().is_success() {
+                return no_store(response);
+            }
+            policy.apply(&mut response);
+            response
+        }).await
+    }
+
+    async fn run(&self, route: &Route, params: PathParams, query_params: HashMap<String, String>) -> Response<Body> {
+        match (route.handler)(self.ctx.clone(), params, query_params).await {
+            Ok(response) => response,
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+// Errors and uncacheable routes must never be stored by the CDN.
+fn no_store(mut response: Response<Body>) -> Response<Body> {
+    response.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+// Runs a route's parameter validation and either dispatches to the handler
+// or fails with a `bad_request` describing the offending parameter.
+fn validated(dispatch: impl FnOnce() -> Result<ResponseFuture, ValidationError>) -> ResponseFuture {
+    match dispatch() {
+        Ok(future) => future,
+        Err(err) => Box::pin(async move { Err(err.into()) }),
     }
-}
\ No newline at end of file
+}
+
+// Splits a path into its segments, ignoring the leading and any trailing slash.
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    let path = path.strip_prefix('/').unwrap_or(path);
+    let path = path.strip_suffix('/').unwrap_or(path);
+    path.split('/').filter(move |_| !path.is_empty())
+}
+
+// Parses a query string into percent-decoded key/value pairs. A `+` is
+// treated as an encoded space, as browsers send it for form input.
+fn parse_query(query: Option<&str>) -> HashMap<String, String> {
+    let decode_component = |raw: &str| {
+        let raw = raw.replace('+', " ");
+        decode(&raw).map(|s| s.into_owned()).unwrap_or(raw)
+    };
+
+    query
+        .map(|query| {
+            query.split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let mut iter = pair.splitn(2, '=');
+                    let key = decode_component(iter.next().unwrap_or(""));
+                    let value = decode_component(iter.next().unwrap_or(""));
+                    (key, value)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,33 @@
+use hyper::{Body, Response};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::context::AppContext;
+use crate::error::AppError;
+use crate::response;
+
+pub fn fetch_suggestions(ctx: Arc<AppContext>, text: String, size: usize) -> Pin<Box<dyn Future<Output = Result<Response<Body>, AppError>> + Send>> {
+    Box::pin(async move {
+        let suggestions = ctx.backend.suggest(text, size).await?;
+
+        // Only what the search box shows and links to
+        let response_body = json!({
+            "message": "Successfully",
+            "error": false,
+            "data": {
+                "stories": titles(&suggestions.stories),
+                "authors": titles(&suggestions.authors),
+                "categories": titles(&suggestions.categories)
+            }
+        });
+
+        Ok(response::json(response_body.to_string()))
+    })
+}
+
+fn titles(docs: &[Value]) -> Vec<Value> {
+    docs.iter()
+        .map(|doc| json!({ "title": doc["title"], "url_key": doc["url_key"] }))
+        .collect()
+}
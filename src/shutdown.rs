@@ -0,0 +1,62 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Resolves on the first SIGINT (Ctrl-C) or, on Unix, SIGTERM.
+pub async fn signal() {
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %err, "failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => tracing::info!(signal = "SIGINT", "shutdown requested"),
+        _ = terminate => tracing::info!(signal = "SIGTERM", "shutdown requested"),
+    }
+}
+
+/// Runs a gracefully shutting down `server` to completion, but gives up
+/// waiting for in-flight requests `timeout` after `draining` fires.
+pub async fn drain<F>(server: F, draining: oneshot::Receiver<()>, timeout: Duration) -> Result<(), hyper::Error>
+where
+    F: Future<Output = Result<(), hyper::Error>>,
+{
+    let deadline = async {
+        // Dropped unsent: the server stopped on its own, nothing to wait for
+        if draining.await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(timeout).await;
+    };
+
+    tokio::select! {
+        result = server => {
+            tracing::info!("server stopped");
+            result
+        }
+        _ = deadline => {
+            tracing::warn!(
+                timeout_ms = timeout.as_millis() as u64,
+                "in-flight requests did not finish before the shutdown deadline"
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use hyper::body::Bytes;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::Instrument;
+use crate::response_cache::CachedResponse;
+
+// How often files past their stale lifetime are deleted
+const SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// One JSON file per cache key, holding the last successful response so it
+/// can still be served stale after a restart or an LRU eviction.
+///
+/// Keys include free-text queries, so the directory is bounded: files are
+/// deleted once they can no longer be served, and no new ones are written
+/// while `max_files` are kept.
+#[derive(Clone)]
+pub struct StaleStore {
+    dir: PathBuf,
+    max_age: Duration,
+    max_files: usize,
+    // Files in `dir`, as of the last sweep plus those written since
+    files: Arc<AtomicUsize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredResponse {
+    // Guards against hash collisions
+    key: String,
+    // Unix seconds
+    expires_at: u64,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl StaleStore {
+    pub fn new(dir: PathBuf, max_age: Duration, max_files: usize) -> StaleStore {
+        StaleStore { dir, max_age, max_files, files: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    /// Sweeps the directory now, which also counts the files left by an
+    /// earlier run, and then every few minutes.
+    pub fn spawn_sweeper(&self) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            loop {
+                store.sweep().await;
+                tokio::time::sleep(SWEEP_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Writes in the background; a failed write only costs a stale fallback.
+    /// Bodies that are not UTF-8 are not stored, nor new keys while the
+    /// store is full.
+    pub fn save(&self, key: &str, response: &CachedResponse, expires_at: SystemTime) {
+        let body = match std::str::from_utf8(response.body()) {
+            Ok(body) => body.to_string(),
+            Err(_) => return,
+        };
+        let stored = StoredResponse {
+            key: key.to_string(),
+            expires_at: unix_secs(expires_at),
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect(),
+            body,
+        };
+        let contents = match serde_json::to_vec(&stored) {
+            Ok(contents) => contents,
+            Err(_) => return,
+        };
+
+        let dir = self.dir.clone();
+        let path = self.path(key);
+        let files = self.files.clone();
+        let max_files = self.max_files;
+        tokio::spawn(async move {
+            let is_new = !tokio::fs::try_exists(&path).await.unwrap_or(false);
+            if is_new && files.load(Ordering::Relaxed) >= max_files {
+                tracing::debug!(path = %path.display(), "stale store is full, not writing");
+                return;
+            }
+            // Write then rename, so readers never see a half-written file
+            let tmp = path.with_extension("tmp");
+            let result = async {
+                tokio::fs::create_dir_all(&dir).await?;
+                tokio::fs::write(&tmp, contents).await?;
+                tokio::fs::rename(&tmp, &path).await
+            };
+            match result.await {
+                Ok(()) if is_new => {
+                    files.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(()) => {}
+                Err(err) => tracing::warn!(path = %path.display(), error = %err, "failed to write stale response"),
+            }
+        }.in_current_span());
+    }
+
+    /// The stored response for `key`, unless it expired more than `max_age`
+    /// ago, in which case the file is deleted.
+    pub async fn load(&self, key: &str) -> Option<CachedResponse> {
+        let path = self.path(key);
+        let contents = tokio::fs::read(&path).await.ok()?;
+        let stored: StoredResponse = serde_json::from_slice(&contents).ok()?;
+        if stored.key != key {
+            return None;
+        }
+        if self.is_expired(stored.expires_at) {
+            self.remove(&path).await;
+            return None;
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &stored.headers {
+            if let (Ok(name), Ok(value)) = (name.parse::<HeaderName>(), HeaderValue::from_str(value)) {
+                headers.append(name, value);
+            }
+        }
+        let status = StatusCode::from_u16(stored.status).ok()?;
+
+        Some(CachedResponse::new(status, headers, Bytes::from(stored.body)))
+    }
+
+    /// Deletes files that can no longer be served, or cannot be read, then
+    /// the ones expiring first until at most `max_files` are left.
+    pub async fn sweep(&self) {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            // Nothing written yet
+            Err(_) => return,
+        };
+
+        let mut kept = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let expires_at = tokio::fs::read(&path)
+                .await
+                .ok()
+                .and_then(|contents| serde_json::from_slice::<StoredResponse>(&contents).ok())
+                .map(|stored| stored.expires_at);
+            match expires_at {
+                Some(expires_at) if !self.is_expired(expires_at) => kept.push((expires_at, path)),
+                _ => {
+                    let _ = tokio::fs::remove_file(&path).await;
+                }
+            }
+        }
+
+        kept.sort();
+        let excess = kept.len().saturating_sub(self.max_files);
+        for (_, path) in kept.drain(..excess) {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        self.files.store(kept.len(), Ordering::Relaxed);
+    }
+
+    fn is_expired(&self, expires_at: u64) -> bool {
+        unix_secs(SystemTime::now()) >= expires_at.saturating_add(self.max_age.as_secs())
+    }
+
+    async fn remove(&self, path: &Path) {
+        if tokio::fs::remove_file(path).await.is_ok() {
+            let _ = self.files.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |files| files.checked_sub(1));
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let digest = Sha256::digest(key.as_bytes());
+        let name: String = digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
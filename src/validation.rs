@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::config::PaginationConfig;
+
+// Longest id or url_key accepted in a path or query parameter
+const MAX_ID_LEN: usize = 200;
+// Longest free-text search accepted
+const MAX_TEXT_LEN: usize = 256;
+
+/// A request parameter that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub parameter: String,
+    pub reason: String,
+}
+
+impl ValidationError {
+    pub fn new(parameter: &str, reason: impl Into<String>) -> Self {
+        ValidationError { parameter: parameter.to_string(), reason: reason.into() }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid parameter `{}`: {}", self.parameter, self.reason)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A validated page request. `page` is 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: usize,
+    pub size: usize,
+}
+
+/// Reads `page` and `size`, defaulting to page 1 of `default_size` items.
+/// `size` is capped by `max_page_size` and the last requested item by
+/// `max_result_window`.
+pub fn pagination(
+    query_params: &HashMap<String, String>,
+    default_size: usize,
+    limits: &PaginationConfig,
+) -> Result<Pagination, ValidationError> {
+    let page = positive_int(query_params, "page")?.unwrap_or(1);
+    let size = positive_int(query_params, "size")?.unwrap_or(default_size);
+
+    if size > limits.max_page_size {
+        return Err(ValidationError::new(
+            "size",
+            format!("must not exceed {}", limits.max_page_size),
+        ));
+    }
+    let window_end = page.checked_mul(size).filter(|end| *end <= limits.max_result_window);
+    if window_end.is_none() {
+        return Err(ValidationError::new(
+            "page",
+            format!("page * size must not exceed {}", limits.max_result_window),
+        ));
+    }
+
+    Ok(Pagination { page, size })
+}
+
+/// Reads a boolean flag. Missing or empty means `false`; otherwise only
+/// `true`/`false`/`1`/`0` are accepted.
+pub fn flag(query_params: &HashMap<String, String>, name: &str) -> Result<bool, ValidationError> {
+    match query_params.get(name).map(|v| v.trim()) {
+        None | Some("") => Ok(false),
+        Some("true") | Some("1") => Ok(true),
+        Some("false") | Some("0") => Ok(false),
+        Some(_) => Err(ValidationError::new(name, "must be `true` or `false`")),
+    }
+}
+
+/// Checks an id or url_key taken from the path or the query string.
+pub fn id(name: &str, value: &str) -> Result<String, ValidationError> {
+    let value = value.trim();
+
+    if value.is_empty() {
+        return Err(ValidationError::new(name, "must not be empty"));
+    }
+    if value.chars().count() > MAX_ID_LEN {
+        return Err(ValidationError::new(name, format!("must be at most {} characters", MAX_ID_LEN)));
+    }
+    if value.chars().any(|c| c.is_control()) {
+        return Err(ValidationError::new(name, "must not contain control characters"));
+    }
+
+    Ok(value.to_string())
+}
+
+/// An optional id in the query string; empty counts as missing.
+pub fn optional_id(query_params: &HashMap<String, String>, name: &str) -> Result<Option<String>, ValidationError> {
+    match query_params.get(name) {
+        Some(value) if !value.trim().is_empty() => id(name, value).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Optional free text, such as a search term; empty counts as missing.
+pub fn optional_text(query_params: &HashMap<String, String>, name: &str) -> Result<Option<String>, ValidationError> {
+    let value = match query_params.get(name).map(|v| v.trim()) {
+        Some(value) if !value.is_empty() => value,
+        _ => return Ok(None),
+    };
+
+    if value.chars().count() > MAX_TEXT_LEN {
+        return Err(ValidationError::new(name, format!("must be at most {} characters", MAX_TEXT_LEN)));
+    }
+
+    Ok(Some(value.to_string()))
+}
+
+/// Required free text; missing or empty is an error.
+pub fn text(query_params: &HashMap<String, String>, name: &str) -> Result<String, ValidationError> {
+    optional_text(query_params, name)?.ok_or_else(|| ValidationError::new(name, "is required"))
+}
+
+/// Reads an optional result count, defaulting to `default` and capped by
+/// `max`.
+pub fn limit(
+    query_params: &HashMap<String, String>,
+    name: &str,
+    default: usize,
+    max: usize,
+) -> Result<usize, ValidationError> {
+    let limit = positive_int(query_params, name)?.unwrap_or(default);
+    if limit > max {
+        return Err(ValidationError::new(name, format!("must not exceed {}", max)));
+    }
+
+    Ok(limit)
+}
+
+fn positive_int(query_params: &HashMap<String, String>, name: &str) -> Result<Option<usize>, ValidationError> {
+    match query_params.get(name).map(|v| v.trim()) {
+        None | Some("") => Ok(None),
+        Some(value) => match value.parse::<usize>() {
+            Ok(n) if n >= 1 => Ok(Some(n)),
+            _ => Err(ValidationError::new(name, "must be a positive integer")),
+        },
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{fixtures_config, header, spawn_app};
+use reqwest::Response;
+
+async fn app() -> String {
+    spawn_app(fixtures_config()).await
+}
+
+async fn get(url: &str) -> Response {
+    reqwest::get(url).await.expect("request failed")
+}
+
+#[tokio::test]
+async fn routes_declare_their_cache_lifetimes() {
+    let app = app().await;
+
+    let cases = [
+        ("/categories/list", "public, max-age=3600, s-maxage=3600"),
+        ("/chapters/detail_by_url/tien-nghich/chuong-1", "public, max-age=86400, s-maxage=86400"),
+        ("/stories/list", "public, max-age=60, s-maxage=60, stale-while-revalidate=300"),
+    ];
+    for (path, expected) in cases {
+        let res = get(&format!("{}{}", app, path)).await;
+
+        assert_eq!(res.status(), 200, "{}", path);
+        assert_eq!(header(&res, "cache-control"), Some(expected), "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn responses_are_tagged_by_story_and_chapter() {
+    let app = app().await;
+
+    let chapter = get(&format!("{}/chapters/detail_by_url/tien-nghich/chuong-1", app)).await;
+    assert_eq!(header(&chapter, "surrogate-key"), Some("story-1 chapter-101"));
+
+    let chapters = get(&format!("{}/chapters/list/1", app)).await;
+    assert_eq!(header(&chapters, "surrogate-key"), Some("story-1"));
+
+    let story = get(&format!("{}/stories/detail_by_url_key/cau-ma", app)).await;
+    assert_eq!(header(&story, "surrogate-key"), Some("story-2"));
+
+    let list = get(&format!("{}/stories/list_by_category/2", app)).await;
+    assert_eq!(header(&list, "surrogate-key"), Some("story-2 story-3 stories"));
+
+    let categories = get(&format!("{}/categories/list", app)).await;
+    assert_eq!(header(&categories, "surrogate-key"), Some("categories"));
+}
+
+#[tokio::test]
+async fn errors_are_not_cacheable() {
+    let app = app().await;
+
+    for path in ["/stories/detail_by_url_key/nope", "/stories/list?page=0", "/nope"] {
+        let res = get(&format!("{}{}", app, path)).await;
+
+        assert!(res.status().is_client_error(), "{}", path);
+        assert_eq!(header(&res, "cache-control"), Some("no-store"), "{}", path);
+        assert_eq!(header(&res, "surrogate-key"), None, "{}", path);
+    }
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{get, hits, spawn_app, test_config, MockEs};
+use serde_json::json;
+
+#[tokio::test]
+async fn list_sorts_by_order_and_strips_content() {
+    let es = MockEs::start().await;
+    es.respond("/chapters/_search", 200, hits(vec![json!({
+        "chapter_id": "101",
+        "story_id": "1",
+        "title": "Chương 1",
+        "url_key": "chuong-1",
+        "ordered": 1,
+        "content": "a very long chapter body"
+    })], 120));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/chapters/list/1?page=2", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.last_body("/chapters/_search"), json!({
+        "query": { "term": { "story_id": "1" } },
+        "from": 50,
+        "size": 50,
+        "sort": [{ "ordered": { "order": "asc" } }]
+    }));
+    assert_eq!(body["data"]["total"], 120);
+    assert_eq!(body["data"]["total_page"], 3);
+    let chapter = &body["data"]["list"][0];
+    assert_eq!(chapter["chapter_id"], "101");
+    assert!(chapter.get("content").is_none());
+}
+
+#[tokio::test]
+async fn detail_matches_story_and_chapter_keys() {
+    let es = MockEs::start().await;
+    es.respond("/chapters/_search", 200, hits(vec![json!({ "url_key": "chuong-1", "content": "..." })], 1));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/chapters/detail_by_url/tien-nghich/chuong-1", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["content"], "...");
+    assert_eq!(es.last_body("/chapters/_search"), json!({
+        "query": {
+            "bool": {
+                "must": [
+                    { "term": { "story_url_key": "tien-nghich" } },
+                    { "term": { "url_key": "chuong-1" } }
+                ]
+            }
+        },
+        "size": 1
+    }));
+}
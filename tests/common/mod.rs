@@ -0,0 +1,175 @@
+#![allow(dead_code)]
+
+use comic_es::config::{BackendKind, Config};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A request received by the mock Elasticsearch.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    // `Null` unless the body is a single JSON document
+    pub body: Value,
+    // As sent, e.g. for `_msearch` NDJSON
+    pub raw_body: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    requests: Vec<RecordedRequest>,
+    responses: HashMap<String, (u16, Value)>,
+    // (path, text the body contains, response), checked first
+    body_responses: Vec<(String, String, (u16, Value))>,
+    delay: Duration,
+}
+
+/// Stub Elasticsearch that records every request and answers with canned
+/// payloads registered per path. Unregistered paths get an empty hit list.
+pub struct MockEs {
+    pub url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockEs {
+    pub async fn start() -> MockEs {
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let make_svc = {
+            let state = state.clone();
+            make_service_fn(move |_conn| {
+                let state = state.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                        handle(state.clone(), req)
+                    }))
+                }
+            })
+        };
+
+        let addr: SocketAddr = ([127, 0, 0, 1], 0).into();
+        let server = Server::bind(&addr).serve(make_svc);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        MockEs { url, state }
+    }
+
+    /// Answers requests to `path` (e.g. `/stories/_search`) with `body`.
+    pub fn respond(&self, path: &str, status: u16, body: Value) {
+        self.state.lock().unwrap().responses.insert(path.to_string(), (status, body));
+    }
+
+    /// Answers requests to `path` whose body contains `needle` with `body`,
+    /// ahead of the path's own response.
+    pub fn respond_when(&self, path: &str, needle: &str, status: u16, body: Value) {
+        self.state.lock().unwrap().body_responses.push((path.to_string(), needle.to_string(), (status, body)));
+    }
+
+    /// Holds every response for `delay` before answering.
+    pub fn delay(&self, delay: Duration) {
+        self.state.lock().unwrap().delay = delay;
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Number of requests sent to `path`.
+    pub fn search_count(&self, path: &str) -> usize {
+        self.requests().iter().filter(|req| req.path == path).count()
+    }
+
+    /// Body of the last request sent to `path`.
+    pub fn last_body(&self, path: &str) -> Value {
+        self.requests()
+            .into_iter()
+            .rev()
+            .find(|req| req.path == path)
+            .map(|req| req.body)
+            .unwrap_or_else(|| panic!("no request was sent to {}", path))
+    }
+}
+
+async fn handle(state: Arc<Mutex<MockState>>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let bytes = hyper::body::to_bytes(req.into_body()).await.unwrap_or_default();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    let raw_body = String::from_utf8_lossy(&bytes).into_owned();
+
+    let ((status, body), delay) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(RecordedRequest { method, path: path.clone(), body, raw_body: raw_body.clone() });
+        let response = state.body_responses
+            .iter()
+            .find(|(candidate, needle, _)| *candidate == path && raw_body.contains(needle.as_str()))
+            .map(|(_, _, response)| response.clone())
+            .or_else(|| state.responses.get(&path).cloned())
+            .unwrap_or_else(|| (200, hits(vec![], 0)));
+        (response, state.delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap())
+}
+
+/// A `_search` response holding `docs` as `_source`s.
+pub fn hits(docs: Vec<Value>, total: u64) -> Value {
+    let hits: Vec<Value> = docs
+        .into_iter()
+        .enumerate()
+        .map(|(i, doc)| json!({ "_index": "test", "_id": i.to_string(), "_source": doc }))
+        .collect();
+
+    json!({ "hits": { "total": { "value": total, "relation": "eq" }, "hits": hits } })
+}
+
+pub fn test_config(es: &MockEs) -> Config {
+    let mut config = Config::default();
+    config.server.listen_addr = ([127, 0, 0, 1], 0).into();
+    config.backend.kind = BackendKind::Elasticsearch;
+    config.elasticsearch.urls = vec![es.url.clone()];
+    config
+}
+
+/// Serves the JSON fixtures from the memory backend, no Elasticsearch needed.
+pub fn fixtures_config() -> Config {
+    let mut config = Config::default();
+    config.server.listen_addr = ([127, 0, 0, 1], 0).into();
+    config.backend.kind = BackendKind::Memory;
+    config.backend.fixtures_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    config
+}
+
+/// Starts the service on a random port and returns its base URL.
+pub async fn spawn_app(config: Config) -> String {
+    let (addr, server) = comic_es::serve(config).expect("failed to start service");
+    tokio::spawn(server);
+
+    format!("http://{}", addr)
+}
+
+/// A response header, which must be valid text.
+pub fn header<'a>(res: &'a reqwest::Response, name: &str) -> Option<&'a str> {
+    res.headers().get(name).map(|value| value.to_str().unwrap())
+}
+
+pub async fn get(url: &str) -> (u16, Value) {
+    let res = reqwest::get(url).await.expect("request failed");
+    let status = res.status().as_u16();
+    let text = res.text().await.unwrap();
+
+    (status, serde_json::from_str(&text).unwrap_or(Value::String(text)))
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, ZstdDecoder};
+use comic_es::compression::{negotiate, Encoding};
+use common::{hits, spawn_app, test_config, MockEs};
+use serde_json::{json, Value};
+use tokio::io::AsyncReadExt;
+
+struct Fetched {
+    encoding: Option<String>,
+    vary: String,
+    bytes: Vec<u8>,
+}
+
+async fn fetch(url: &str, accept_encoding: &str) -> Fetched {
+    let res = reqwest::Client::new()
+        .get(url)
+        .header("Accept-Encoding", accept_encoding)
+        .send()
+        .await
+        .expect("request failed");
+    let header = |name: &str| res.headers().get(name).map(|v| v.to_str().unwrap().to_string());
+    let encoding = header("content-encoding");
+    let vary = res.headers().get_all("vary").iter().map(|v| v.to_str().unwrap()).collect::<Vec<_>>().join(", ");
+
+    Fetched { encoding, vary, bytes: res.bytes().await.unwrap().to_vec() }
+}
+
+async fn decode(encoding: &str, bytes: &[u8]) -> Value {
+    let mut out = Vec::new();
+    match encoding {
+        "gzip" => GzipDecoder::new(bytes).read_to_end(&mut out).await,
+        "br" => BrotliDecoder::new(bytes).read_to_end(&mut out).await,
+        "zstd" => ZstdDecoder::new(bytes).read_to_end(&mut out).await,
+        other => panic!("unexpected encoding {}", other),
+    }
+    .expect("body does not decode");
+
+    serde_json::from_slice(&out).unwrap()
+}
+
+async fn app_with_stories(count: usize) -> (MockEs, String) {
+    let es = MockEs::start().await;
+    let docs = (0..count).map(|i| json!({ "title": format!("Truyện số {}", i) })).collect();
+    es.respond("/stories/_search", 200, hits(docs, count as u64));
+    let app = spawn_app(test_config(&es)).await;
+
+    (es, app)
+}
+
+#[test]
+fn negotiation_honours_q_values() {
+    assert_eq!(negotiate("gzip"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+    assert_eq!(negotiate("br;q=0.5, gzip;q=0.8"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("zstd, gzip;q=0.9"), Some(Encoding::Zstd));
+    assert_eq!(negotiate("br;q=0, *;q=0.1"), Some(Encoding::Zstd));
+    assert_eq!(negotiate("x-gzip"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("identity"), None);
+    assert_eq!(negotiate("gzip;q=0"), None);
+    assert_eq!(negotiate(""), None);
+}
+
+#[tokio::test]
+async fn large_bodies_are_compressed_with_the_negotiated_coding() {
+    let (_es, app) = app_with_stories(100).await;
+
+    for encoding in ["gzip", "br", "zstd"] {
+        let res = fetch(&format!("{}/stories/list?size=100", app), encoding).await;
+
+        assert_eq!(res.encoding.as_deref(), Some(encoding));
+        assert_eq!(res.vary, "origin, accept-encoding");
+        let body = decode(encoding, &res.bytes).await;
+        assert_eq!(body["data"]["list"][99]["title"], "Truyện số 99");
+    }
+}
+
+#[tokio::test]
+async fn small_bodies_are_sent_as_is() {
+    let (_es, app) = app_with_stories(1).await;
+
+    let res = fetch(&format!("{}/stories/list", app), "gzip").await;
+
+    assert_eq!(res.encoding, None);
+    assert_eq!(res.vary, "origin, accept-encoding");
+    let body: Value = serde_json::from_slice(&res.bytes).unwrap();
+    assert_eq!(body["data"]["total"], 1);
+}
+
+#[tokio::test]
+async fn unsupported_codings_are_sent_as_is() {
+    let (_es, app) = app_with_stories(100).await;
+
+    let res = fetch(&format!("{}/stories/list?size=100", app), "deflate, gzip;q=0").await;
+
+    assert_eq!(res.encoding, None);
+    let body: Value = serde_json::from_slice(&res.bytes).unwrap();
+    assert_eq!(body["data"]["total"], 100);
+}
+
+#[tokio::test]
+async fn compression_can_be_disabled() {
+    let es = MockEs::start().await;
+    let docs = (0..100).map(|i| json!({ "title": format!("Truyện số {}", i) })).collect();
+    es.respond("/stories/_search", 200, hits(docs, 100));
+    let mut config = test_config(&es);
+    config.compression.enabled = false;
+    let app = spawn_app(config).await;
+
+    let res = fetch(&format!("{}/stories/list?size=100", app), "gzip").await;
+
+    assert_eq!(res.encoding, None);
+    assert_eq!(res.vary, "origin");
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::{fixtures_config, header, spawn_app};
+use reqwest::Response;
+
+async fn app() -> String {
+    spawn_app(fixtures_config()).await
+}
+
+async fn get(url: &str, headers: &[(&str, &str)]) -> Response {
+    let mut req = reqwest::Client::new().get(url);
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+    req.send().await.expect("request failed")
+}
+
+#[tokio::test]
+async fn details_carry_validators() {
+    let app = app().await;
+
+    let res = get(&format!("{}/stories/detail_by_url_key/tien-nghich", app), &[]).await;
+
+    assert_eq!(res.status(), 200);
+    let etag = header(&res, "etag").unwrap().to_string();
+    assert!(etag.starts_with('"') && etag.ends_with('"') && etag.len() == 34, "{}", etag);
+    assert_eq!(header(&res, "last-modified").unwrap(), "Sun, 10 Mar 2024 12:30:00 GMT");
+
+    // The same body always hashes to the same tag
+    let again = get(&format!("{}/stories/detail_by_url_key/tien-nghich", app), &[]).await;
+    assert_eq!(header(&again, "etag").unwrap(), etag);
+}
+
+#[tokio::test]
+async fn matching_etag_is_not_modified() {
+    let app = app().await;
+    let url = format!("{}/chapters/detail_by_url/tien-nghich/chuong-1", app);
+    let etag = header(&get(&url, &[]).await, "etag").unwrap().to_string();
+
+    let res = get(&url, &[("If-None-Match", &format!("\"other\", {}", etag))]).await;
+
+    assert_eq!(res.status(), 304);
+    assert_eq!(header(&res, "etag").unwrap(), etag);
+    assert!(res.bytes().await.unwrap().is_empty());
+
+    let stale = get(&url, &[("If-None-Match", "\"other\"")]).await;
+    assert_eq!(stale.status(), 200);
+}
+
+#[tokio::test]
+async fn etag_differs_per_content_coding() {
+    let mut config = fixtures_config();
+    config.compression.min_size = 0;
+    let app = spawn_app(config).await;
+    let url = format!("{}/stories/detail_by_url_key/tien-nghich", app);
+
+    let identity = get(&url, &[("Accept-Encoding", "identity")]).await;
+    let gzip = get(&url, &[("Accept-Encoding", "gzip")]).await;
+
+    assert_eq!(header(&gzip, "content-encoding").unwrap(), "gzip");
+    let etag = header(&identity, "etag").unwrap().to_string();
+    let gzip_etag = header(&gzip, "etag").unwrap().to_string();
+    assert_eq!(gzip_etag, format!("{}-gzip\"", etag.trim_end_matches('"')));
+
+    // Either validator still matches the same document
+    let res = get(&url, &[("Accept-Encoding", "gzip"), ("If-None-Match", &gzip_etag)]).await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(header(&res, "etag").unwrap(), gzip_etag);
+    let res = get(&url, &[("Accept-Encoding", "identity"), ("If-None-Match", &gzip_etag)]).await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(header(&res, "etag").unwrap(), etag);
+}
+
+#[tokio::test]
+async fn if_modified_since_is_honoured() {
+    let app = app().await;
+    let url = format!("{}/stories/detail_by_url_key/tien-nghich", app);
+
+    let res = get(&url, &[("If-Modified-Since", "Sun, 10 Mar 2024 12:30:00 GMT")]).await;
+    assert_eq!(res.status(), 304);
+
+    let res = get(&url, &[("If-Modified-Since", "Sat, 09 Mar 2024 00:00:00 GMT")]).await;
+    assert_eq!(res.status(), 200);
+
+    // If-None-Match takes precedence
+    let res = get(&url, &[
+        ("If-Modified-Since", "Sun, 10 Mar 2024 12:30:00 GMT"),
+        ("If-None-Match", "\"other\""),
+    ]).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn category_and_author_details_have_etags() {
+    let app = app().await;
+
+    for path in ["/categories/detail_by_url_key/tien-hiep", "/authors/detail_by_url_key/nhi-can"] {
+        let url = format!("{}{}", app, path);
+        let etag = header(&get(&url, &[]).await, "etag").unwrap().to_string();
+
+        let res = get(&url, &[("If-None-Match", &etag)]).await;
+        assert_eq!(res.status(), 304, "{}", path);
+    }
+}
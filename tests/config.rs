@@ -0,0 +1,96 @@
+use comic_es::config::{BackendKind, Config, ConfigError};
+use std::path::PathBuf;
+
+fn example_file() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config.example.toml")
+}
+
+#[test]
+fn example_config_is_valid() {
+    let config = Config::from_file(&example_file()).unwrap();
+
+    config.validate().unwrap();
+    assert_eq!(config.backend.kind, BackendKind::Elasticsearch);
+    assert_eq!(config.server.listen_addr.port(), 8084);
+    assert_eq!(config.elasticsearch.indices.chapters, "chapters");
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let dir = std::env::temp_dir().join(format!("comic-es-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("typo.toml");
+    std::fs::write(&path, "[elasticsearch]\nurll = \"http://es:9200\"\n").unwrap();
+
+    let err = Config::from_file(&path).unwrap_err();
+
+    assert!(matches!(err, ConfigError::File { .. }), "{}", err);
+}
+
+#[test]
+fn single_elasticsearch_url_is_accepted() {
+    let dir = std::env::temp_dir().join(format!("comic-es-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("single-url.toml");
+    std::fs::write(&path, "[elasticsearch]\nurl = \"http://es:9200\"\n").unwrap();
+
+    let config = Config::from_file(&path).unwrap();
+
+    assert_eq!(config.elasticsearch.urls, vec!["http://es:9200".to_string()]);
+}
+
+#[test]
+fn credentials_must_come_in_pairs() {
+    let mut config = Config::default();
+    config.elasticsearch.username = Some("elastic".to_string());
+
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+}
+
+#[test]
+fn default_page_size_cannot_exceed_maximum() {
+    let mut config = Config::default();
+    config.pagination.default_page_size = 500;
+
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+}
+
+#[test]
+fn elasticsearch_url_must_be_http() {
+    let mut config = Config::default();
+    config.elasticsearch.urls = vec!["localhost:9200".to_string()];
+
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+    config.elasticsearch.urls = Vec::new();
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+}
+
+#[test]
+fn cors_credentials_need_explicit_origins() {
+    let mut config = Config::default();
+    config.cors.allow_credentials = true;
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+    config.cors.allowed_origins = vec!["https://*.example.com".to_string()];
+    config.validate().unwrap();
+
+    config.cors.allowed_origins = vec!["example.com".to_string()];
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+}
+
+#[test]
+fn retry_backoff_doubles_up_to_the_cap() {
+    let retry = Config::default().elasticsearch.retry;
+
+    assert_eq!(retry.max_backoff(0).as_millis(), 100);
+    assert_eq!(retry.max_backoff(1).as_millis(), 200);
+    assert_eq!(retry.max_backoff(10).as_millis(), 2_000);
+}
+
+#[test]
+fn readiness_fails_before_the_listener_closes_by_default() {
+    let config = Config::default();
+
+    assert!(config.server.shutdown_delay_ms >= 1000, "{}", config.server.shutdown_delay_ms);
+}
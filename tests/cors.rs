@@ -0,0 +1,116 @@
+mod common;
+
+use common::{header, spawn_app, test_config, MockEs};
+use reqwest::{Method, Response};
+
+async fn app() -> (MockEs, String) {
+    let es = MockEs::start().await;
+    let mut config = test_config(&es);
+    config.cors.allowed_origins = vec!["https://truyen.example".to_string(), "https://*.example.com".to_string()];
+    config.cors.allowed_headers = vec!["Content-Type".to_string(), "Authorization".to_string()];
+    config.cors.allow_credentials = true;
+    config.cors.max_age_secs = 3600;
+    let app = spawn_app(config).await;
+
+    (es, app)
+}
+
+async fn send(method: Method, url: &str, headers: &[(&str, &str)]) -> Response {
+    let mut req = reqwest::Client::new().request(method, url);
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+    req.send().await.expect("request failed")
+}
+
+#[tokio::test]
+async fn allowed_origin_is_echoed_with_credentials() {
+    let (_es, app) = app().await;
+
+    for origin in ["https://truyen.example", "https://m.example.com"] {
+        let res = send(Method::GET, &format!("{}/categories/list", app), &[("Origin", origin)]).await;
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(header(&res, "access-control-allow-origin"), Some(origin));
+        assert_eq!(header(&res, "access-control-allow-credentials"), Some("true"));
+        assert_eq!(header(&res, "vary"), Some("origin"));
+    }
+}
+
+#[tokio::test]
+async fn requests_without_origin_get_no_cors_headers() {
+    let (_es, app) = app().await;
+
+    let res = send(Method::GET, &format!("{}/categories/list", app), &[]).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(header(&res, "access-control-allow-origin"), None);
+    assert_eq!(header(&res, "vary"), Some("origin"));
+}
+
+#[tokio::test]
+async fn disallowed_origins_are_rejected() {
+    let (es, app) = app().await;
+
+    // The bare domain does not match `*.example.com`
+    for origin in ["https://evil.test", "https://example.com", "http://m.example.com"] {
+        let res = send(Method::GET, &format!("{}/categories/list", app), &[("Origin", origin)]).await;
+
+        assert_eq!(res.status(), 403, "{}", origin);
+        assert_eq!(header(&res, "access-control-allow-origin"), None);
+    }
+    assert!(es.requests().is_empty());
+}
+
+#[tokio::test]
+async fn preflight_for_a_route_lists_the_policy() {
+    let (_es, app) = app().await;
+
+    let res = send(Method::OPTIONS, &format!("{}/stories/detail_by_url_key/tien-nghich", app), &[
+        ("Origin", "https://truyen.example"),
+        ("Access-Control-Request-Method", "GET"),
+        ("Access-Control-Request-Headers", "authorization, content-type"),
+    ]).await;
+
+    assert_eq!(res.status(), 204);
+    assert_eq!(header(&res, "access-control-allow-origin"), Some("https://truyen.example"));
+    assert_eq!(header(&res, "access-control-allow-methods"), Some("GET"));
+    assert_eq!(header(&res, "access-control-allow-headers"), Some("content-type, authorization"));
+    assert_eq!(header(&res, "access-control-max-age"), Some("3600"));
+    assert_eq!(header(&res, "access-control-allow-credentials"), Some("true"));
+}
+
+#[tokio::test]
+async fn preflight_rejects_what_the_policy_does_not_allow() {
+    let (_es, app) = app().await;
+    let url = format!("{}/categories/list", app);
+
+    let cases: [&[(&str, &str)]; 3] = [
+        &[("Origin", "https://evil.test"), ("Access-Control-Request-Method", "GET")],
+        &[("Origin", "https://truyen.example"), ("Access-Control-Request-Method", "DELETE")],
+        &[
+            ("Origin", "https://truyen.example"),
+            ("Access-Control-Request-Method", "GET"),
+            ("Access-Control-Request-Headers", "x-secret"),
+        ],
+    ];
+    for headers in cases {
+        let res = send(Method::OPTIONS, &url, headers).await;
+
+        assert_eq!(res.status(), 403, "{:?}", headers);
+        assert_eq!(header(&res, "access-control-allow-origin"), None);
+    }
+}
+
+#[tokio::test]
+async fn preflight_for_unknown_path_is_not_found() {
+    let (_es, app) = app().await;
+
+    let res = send(Method::OPTIONS, &format!("{}/admin/secret", app), &[
+        ("Origin", "https://truyen.example"),
+        ("Access-Control-Request-Method", "GET"),
+    ]).await;
+
+    assert_eq!(res.status(), 404);
+    assert_eq!(header(&res, "access-control-allow-methods"), None);
+}
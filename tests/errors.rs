@@ -0,0 +1,91 @@
+mod common;
+
+use common::{spawn_app, test_config, MockEs};
+use serde_json::{json, Value};
+
+async fn get_with_headers(url: &str) -> (u16, String, Value) {
+    let res = reqwest::get(url).await.expect("request failed");
+    let status = res.status().as_u16();
+    let content_type = res.headers()["content-type"].to_str().unwrap().to_string();
+    let body = res.json().await.expect("error body is not JSON");
+
+    (status, content_type, body)
+}
+
+#[tokio::test]
+async fn unknown_route_uses_the_error_envelope() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, content_type, body) = get_with_headers(&format!("{}/nope", app)).await;
+
+    assert_eq!(status, 404);
+    assert_eq!(content_type, "application/json");
+    assert_eq!(body, json!({ "message": "Not Found", "error": true, "code": "not_found", "data": null }));
+}
+
+#[tokio::test]
+async fn missing_document_is_not_found() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _, body) = get_with_headers(&format!("{}/chapters/detail_by_url/a/b", app)).await;
+
+    assert_eq!(status, 404);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["message"], "Chapter not found");
+}
+
+#[tokio::test]
+async fn validation_failures_are_bad_requests() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _, body) = get_with_headers(&format!("{}/stories/list?size=abc", app)).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "bad_request");
+    assert_eq!(body["data"]["parameter"], "size");
+}
+
+#[tokio::test]
+async fn upstream_error_status_is_not_leaked() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 500, json!({ "error": { "type": "search_phase_execution_exception" } }));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _, body) = get_with_headers(&format!("{}/stories/list", app)).await;
+
+    assert_eq!(status, 502);
+    assert_eq!(body["code"], "upstream_error");
+    assert!(!body.to_string().contains("search_phase_execution_exception"));
+}
+
+#[tokio::test]
+async fn unreachable_upstream_is_unavailable() {
+    let es = MockEs::start().await;
+    let mut config = test_config(&es);
+    config.elasticsearch.urls = vec!["http://127.0.0.1:1".to_string()];
+    let app = spawn_app(config).await;
+
+    let (status, _, body) = get_with_headers(&format!("{}/categories/list", app)).await;
+
+    assert_eq!(status, 503);
+    assert_eq!(body["code"], "upstream_unavailable");
+    assert!(!body["message"].as_str().unwrap().contains("127.0.0.1"));
+}
+
+#[tokio::test]
+async fn malformed_upstream_response_is_an_upstream_error() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 200, json!({ "hits": { "hits": "oops" } }));
+    es.respond("/categories/_search", 200, json!("not a search response"));
+    let app = spawn_app(test_config(&es)).await;
+
+    for path in ["/stories/list", "/categories/list"] {
+        let (status, _, body) = get_with_headers(&format!("{}{}", app, path)).await;
+
+        assert_eq!(status, 502, "{}", path);
+        assert_eq!(body["code"], "upstream_error");
+    }
+}
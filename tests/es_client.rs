@@ -0,0 +1,52 @@
+mod common;
+
+use comic_es::es_client::{EsClient, EsError};
+use common::{test_config, MockEs};
+use serde_json::json;
+
+#[tokio::test]
+async fn count_wraps_the_query_clause() {
+    let es = MockEs::start().await;
+    es.respond("/chapters/_count", 200, json!({ "count": 42, "_shards": { "total": 1, "successful": 1 } }));
+    let client = EsClient::from_config(&test_config(&es).elasticsearch).unwrap();
+
+    let count = client.count("chapters", &json!({ "term": { "story_id": "1" } })).await.unwrap();
+
+    assert_eq!(count, 42);
+    assert_eq!(es.requests()[0].method, "POST");
+    assert_eq!(es.last_body("/chapters/_count"), json!({ "query": { "term": { "story_id": "1" } } }));
+}
+
+#[tokio::test]
+async fn count_reports_upstream_errors() {
+    let es = MockEs::start().await;
+    es.respond("/chapters/_count", 400, json!({ "error": { "type": "parsing_exception" }, "status": 400 }));
+    let client = EsClient::from_config(&test_config(&es).elasticsearch).unwrap();
+
+    let err = client.count("chapters", &json!({ "nope": {} })).await.unwrap_err();
+
+    assert!(matches!(err, EsError::Status { status, .. } if status == 400));
+}
+
+#[tokio::test]
+async fn mget_keeps_order_and_skips_missing_documents() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_mget", 200, json!({
+        "docs": [
+            { "_index": "stories", "_id": "3", "found": true, "_source": { "title": "Đấu Phá Thương Khung" } },
+            { "_index": "stories", "_id": "9", "found": false },
+            { "_index": "stories", "_id": "1", "found": true, "_source": { "title": "Tiên Nghịch" } }
+        ]
+    }));
+    let client = EsClient::from_config(&test_config(&es).elasticsearch).unwrap();
+    let ids = vec!["3".to_string(), "9".to_string(), "1".to_string()];
+
+    let hits = client.mget("stories", &ids).await.unwrap();
+
+    let found: Vec<(&str, &str)> = hits
+        .iter()
+        .map(|hit| (hit.id.as_str(), hit.source["title"].as_str().unwrap()))
+        .collect();
+    assert_eq!(found, vec![("3", "Đấu Phá Thương Khung"), ("1", "Tiên Nghịch")]);
+    assert_eq!(es.last_body("/stories/_mget"), json!({ "ids": ["3", "9", "1"] }));
+}
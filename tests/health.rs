@@ -0,0 +1,80 @@
+mod common;
+
+use common::{get, spawn_app, test_config, MockEs};
+use serde_json::json;
+
+fn healthy_cluster(es: &MockEs) {
+    es.respond("/_cluster/health", 200, json!({ "cluster_name": "test", "status": "green" }));
+    for index in ["stories", "chapters", "categories", "authors"] {
+        es.respond(&format!("/{}/_alias", index), 200, json!({ index: { "aliases": {} } }));
+    }
+}
+
+#[tokio::test]
+async fn liveness_does_not_touch_the_cluster() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/healthz", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["data"]["status"], "ok");
+    assert!(es.requests().is_empty());
+}
+
+#[tokio::test]
+async fn ready_when_cluster_and_indices_are_there() {
+    let es = MockEs::start().await;
+    healthy_cluster(&es);
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/readyz", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["data"]["status"], "ready");
+    assert_eq!(body["data"]["backend"]["cluster_status"], "green");
+    assert_eq!(body["data"]["indices"]["chapters"], json!({ "index": "chapters", "exists": true }));
+}
+
+#[tokio::test]
+async fn missing_index_is_not_ready() {
+    let es = MockEs::start().await;
+    healthy_cluster(&es);
+    es.respond("/chapters/_alias", 404, json!({ "error": "alias [chapters] missing", "status": 404 }));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/readyz", app)).await;
+
+    assert_eq!(status, 503);
+    assert_eq!(body["data"]["status"], "not_ready");
+    assert_eq!(body["data"]["indices"]["chapters"]["exists"], false);
+    assert_eq!(body["data"]["indices"]["stories"]["exists"], true);
+}
+
+#[tokio::test]
+async fn red_cluster_is_not_ready() {
+    let es = MockEs::start().await;
+    healthy_cluster(&es);
+    es.respond("/_cluster/health", 200, json!({ "status": "red" }));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/readyz", app)).await;
+
+    assert_eq!(status, 503);
+    assert_eq!(body["data"]["backend"]["cluster_status"], "red");
+}
+
+#[tokio::test]
+async fn unreachable_cluster_is_not_ready() {
+    let es = MockEs::start().await;
+    let mut config = test_config(&es);
+    config.elasticsearch.urls = vec!["http://127.0.0.1:1".to_string()];
+    config.elasticsearch.retry.max_retries = 0;
+    let app = spawn_app(config).await;
+
+    let (status, body) = get(&format!("{}/readyz", app)).await;
+
+    assert_eq!(status, 503);
+    assert_eq!(body["code"], "upstream_unavailable");
+    assert_eq!(body["data"]["backend"]["reachable"], false);
+}
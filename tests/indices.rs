@@ -0,0 +1,71 @@
+mod common;
+
+use comic_es::es_client::EsClient;
+use common::{get, spawn_app, test_config, MockEs};
+use serde_json::json;
+
+#[tokio::test]
+async fn index_prefix_and_names_are_applied_to_queries() {
+    let es = MockEs::start().await;
+    let mut config = test_config(&es);
+    config.elasticsearch.index_prefix = "staging_".to_string();
+    config.elasticsearch.indices.stories = "stories_alias".to_string();
+    let app = spawn_app(config).await;
+
+    get(&format!("{}/stories/list", app)).await;
+    get(&format!("{}/chapters/list/1", app)).await;
+
+    let paths: Vec<String> = es.requests().into_iter().map(|req| req.path).collect();
+    assert_eq!(paths, vec!["/staging_stories_alias/_search", "/staging_chapters/_search"]);
+}
+
+#[tokio::test]
+async fn resolve_index_lists_alias_targets() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_alias", 200, json!({
+        "stories_v2": { "aliases": { "stories": {} } },
+        "stories_v1": { "aliases": { "stories": {} } }
+    }));
+    es.respond("/authors/_alias", 404, json!({ "error": "alias [authors] missing", "status": 404 }));
+    let client = EsClient::from_config(&test_config(&es).elasticsearch).unwrap();
+
+    assert_eq!(client.resolve_index("stories").await.unwrap(), vec!["stories_v1", "stories_v2"]);
+    assert!(client.resolve_index("authors").await.unwrap().is_empty());
+    assert_eq!(es.requests()[0].method, "GET");
+}
+
+#[test]
+fn every_search_field_is_mapped() {
+    let mappings = comic_es::mappings::stories();
+
+    for field in comic_es::mappings::STORY_SEARCH_FIELDS {
+        let path = field.split('^').next().unwrap();
+        let mut mapping = &mappings["mappings"];
+        let mut parts = path.split('.').peekable();
+        while let Some(part) = parts.next() {
+            mapping = &mapping["properties"][part];
+            if parts.peek() == Some(&"folded") {
+                mapping = &mapping["fields"]["folded"];
+                assert_eq!(mapping["analyzer"], comic_es::mappings::FOLDED_ANALYZER, "{}", field);
+                break;
+            }
+        }
+        assert_eq!(mapping["type"], "text", "{}", field);
+    }
+    assert_eq!(
+        mappings["settings"]["analysis"]["analyzer"][comic_es::mappings::FOLDED_ANALYZER]["filter"],
+        json!(["lowercase", "asciifolding"])
+    );
+}
+
+#[test]
+fn suggested_titles_complete_as_you_type() {
+    for entity in comic_es::mappings::ENTITIES {
+        let mappings = comic_es::mappings::for_entity(entity).unwrap();
+        let suggest = &mappings["mappings"]["properties"]["title"]["fields"]["suggest"];
+
+        assert_eq!(suggest["type"], "search_as_you_type", "{}", entity);
+        assert_eq!(suggest["analyzer"], comic_es::mappings::FOLDED_ANALYZER, "{}", entity);
+    }
+    assert!(comic_es::mappings::for_entity("chapters").is_none());
+}
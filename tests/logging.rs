@@ -0,0 +1,126 @@
+mod common;
+
+use comic_es::config::{Config, LoggingConfig};
+use common::{header, hits, spawn_app, test_config, MockEs};
+use serde_json::{json, Value};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+// Collects everything the subscriber writes.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn lines(&self) -> Vec<Value> {
+        let bytes = self.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+}
+
+// Tests run on a single-threaded runtime, so a thread-local subscriber sees
+// the service's events too.
+fn capture(level: &str) -> (Captured, tracing::subscriber::DefaultGuard) {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let config = LoggingConfig { level: level.to_string(), ..LoggingConfig::default() };
+    let guard = tracing::subscriber::set_default(comic_es::logging::subscriber(&config, move || writer.clone()));
+
+    (captured, guard)
+}
+
+async fn app_with_stories(es: &MockEs) -> String {
+    es.respond("/stories/_search", 200, hits(vec![json!({ "title": "Đấu Phá Thương Khung" })], 1));
+    let mut config: Config = test_config(es);
+    config.cache.enabled = false;
+    spawn_app(config).await
+}
+
+async fn fetch(url: &str, request_id: Option<&str>) -> reqwest::Response {
+    let mut request = reqwest::Client::new().get(url);
+    if let Some(id) = request_id {
+        request = request.header("X-Request-Id", id);
+    }
+    let res = request.send().await.expect("request failed");
+    assert_eq!(res.status(), 200);
+    res
+}
+
+#[tokio::test]
+async fn request_id_is_generated_and_echoed() {
+    let es = MockEs::start().await;
+    let app = app_with_stories(&es).await;
+
+    let res = fetch(&format!("{}/stories/list", app), None).await;
+
+    let id = header(&res, "x-request-id").unwrap().to_string();
+    assert_eq!(id.len(), 32);
+    assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+}
+
+#[tokio::test]
+async fn request_id_is_propagated() {
+    let es = MockEs::start().await;
+    let app = app_with_stories(&es).await;
+
+    let res = fetch(&format!("{}/stories/list", app), Some("edge-42.abc")).await;
+    assert_eq!(header(&res, "x-request-id").unwrap(), "edge-42.abc");
+
+    let res = fetch(&format!("{}/stories/list", app), Some("not a valid id")).await;
+    assert_ne!(header(&res, "x-request-id").unwrap(), "not a valid id");
+}
+
+#[tokio::test]
+async fn every_request_gets_an_access_log_line() {
+    let (captured, _guard) = capture("info");
+    let es = MockEs::start().await;
+    let app = app_with_stories(&es).await;
+
+    let res = fetch(&format!("{}/stories/list_by_category/7", app), Some("access-1")).await;
+    let body = res.bytes().await.unwrap();
+
+    let lines = captured.lines();
+    let access = lines
+        .iter()
+        .find(|line| line["target"] == "comic_es::access")
+        .expect("no access log line");
+    let fields = &access["fields"];
+    assert_eq!(access["level"], "INFO");
+    assert_eq!(fields["request_id"], "access-1");
+    assert_eq!(fields["method"], "GET");
+    assert_eq!(fields["path"], "/stories/list_by_category/7");
+    assert_eq!(fields["route"], "/stories/list_by_category/{category_id}");
+    assert_eq!(fields["status"], 200);
+    assert_eq!(fields["bytes"], body.len() as u64);
+    assert!(fields["duration_ms"].as_f64().is_some());
+    // Elasticsearch queries stay out of info logs
+    assert!(lines.iter().all(|line| line["fields"]["message"] != "Elasticsearch search"));
+}
+
+#[tokio::test]
+async fn queries_are_logged_at_debug_with_the_request_id() {
+    let (captured, _guard) = capture("debug");
+    let es = MockEs::start().await;
+    let app = app_with_stories(&es).await;
+
+    fetch(&format!("{}/stories/list", app), Some("debug-1")).await;
+
+    let lines = captured.lines();
+    let query = lines
+        .iter()
+        .find(|line| line["fields"]["message"] == "Elasticsearch search")
+        .expect("no query log line");
+    assert_eq!(query["level"], "DEBUG");
+    assert_eq!(query["fields"]["index"], "stories");
+    assert_eq!(query["span"]["request_id"], "debug-1");
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::{fixtures_config, get, spawn_app};
+use comic_es::backend::{MemoryBackend, SearchBackend, StoryQuery};
+use serde_json::json;
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn serves_story_search_from_fixtures() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (status, body) = get(&format!("{}/stories/list?title=c%E1%BA%A7u+ma", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["data"]["total"], 1);
+    assert_eq!(body["data"]["list"][0]["url_key"], "cau-ma");
+}
+
+#[tokio::test]
+async fn story_search_ignores_missing_diacritics() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (_, body) = get(&format!("{}/stories/list?title=tien+nghich", app)).await;
+    assert_eq!(body["data"]["list"][0]["url_key"], "tien-nghich");
+
+    let (_, body) = get(&format!("{}/stories/list?title=dau+pha", app)).await;
+    assert_eq!(body["data"]["total"], 1);
+    assert_eq!(body["data"]["list"][0]["url_key"], "dau-pha-thuong-khung");
+}
+
+#[tokio::test]
+async fn story_search_covers_alternate_titles_and_authors() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (_, body) = get(&format!("{}/stories/list?title=renegade+immortal", app)).await;
+    assert_eq!(body["data"]["total"], 1);
+    assert_eq!(body["data"]["list"][0]["url_key"], "tien-nghich");
+
+    let (_, body) = get(&format!("{}/stories/list?title=nhi+can", app)).await;
+    assert_eq!(body["data"]["total"], 2);
+}
+
+#[tokio::test]
+async fn misspelled_story_search_suggests_the_title() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (_, body) = get(&format!("{}/stories/list?title=tieen+nghjch", app)).await;
+
+    assert_eq!(body["data"]["total"], 0);
+    assert_eq!(body["data"]["suggestions"], json!(["tiên nghịch"]));
+}
+
+#[tokio::test]
+async fn highlighted_story_search_marks_matches() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (_, body) = get(&format!("{}/stories/list?title=tien&highlight=true", app)).await;
+
+    assert_eq!(body["data"]["list"][0]["_highlight"], json!({
+        "title": ["<em>Tiên</em> Nghịch"],
+        "description": ["<em>tiên</em>."]
+    }));
+
+    let (_, body) = get(&format!("{}/stories/list?title=tien", app)).await;
+    assert!(body["data"]["list"][0].get("_highlight").is_none());
+}
+
+#[tokio::test]
+async fn highlighted_fragments_escape_html() {
+    let dir = std::env::temp_dir().join(format!("comic-es-highlight-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("stories.json"), json!([{
+        "story_id": 1,
+        "title": "Tiên <script>alert('x')</script>",
+        "description": "Tiên & ma"
+    }]).to_string()).unwrap();
+    let mut config = fixtures_config();
+    config.backend.fixtures_dir = dir;
+    let app = spawn_app(config).await;
+
+    let (_, body) = get(&format!("{}/stories/list?title=tien&highlight=true", app)).await;
+
+    assert_eq!(body["data"]["list"][0]["_highlight"], json!({
+        "title": ["<em>Tiên</em> &lt;script&gt;alert(&#x27;x&#x27;)&lt;&#x2F;script&gt;"],
+        "description": ["<em>Tiên</em> &amp; ma"]
+    }));
+}
+
+#[tokio::test]
+async fn serves_chapters_in_order() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (status, body) = get(&format!("{}/chapters/list/1", app)).await;
+
+    assert_eq!(status, 200);
+    let keys: Vec<_> = body["data"]["list"].as_array().unwrap().iter().map(|c| c["url_key"].clone()).collect();
+    assert_eq!(keys, vec![json!("chuong-1"), json!("chuong-2")]);
+}
+
+#[tokio::test]
+async fn unknown_author_is_not_found() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (status, _) = get(&format!("{}/authors/detail_by_url_key/nobody", app)).await;
+
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn page_zero_reads_as_the_first_page() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let backend = MemoryBackend::load(&dir).unwrap();
+
+    let first = backend.search_stories(StoryQuery { page: 1, size: 2, ..StoryQuery::default() }).await.unwrap();
+    let zero = backend.search_stories(StoryQuery { size: 2, ..StoryQuery::default() }).await.unwrap();
+    assert_eq!(zero.items, first.items);
+
+    let story_id = first.items[0]["story_id"].as_str().unwrap().to_string();
+    let chapters = backend.chapters_by_story_id(story_id, 0, 2).await.unwrap();
+    assert!(!chapters.items.is_empty());
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::{get, hits, spawn_app, test_config, MockEs};
+use serde_json::json;
+
+async fn scrape(app: &str) -> String {
+    let res = reqwest::get(format!("{}/metrics", app)).await.expect("request failed");
+    assert_eq!(res.status(), 200);
+    assert!(res.headers()["content-type"].to_str().unwrap().starts_with("text/plain; version=0.0.4"));
+
+    res.text().await.unwrap()
+}
+
+// The value of the sample named exactly `series`, labels included.
+fn sample(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn requests_are_counted_per_route_template() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    get(&format!("{}/stories/detail_by_url_key/a", app)).await;
+    get(&format!("{}/stories/detail_by_url_key/b", app)).await;
+    get(&format!("{}/no/such/path", app)).await;
+    let metrics = scrape(&app).await;
+
+    let route = r#"method="GET",route="/stories/detail_by_url_key/{url_key}",status="404""#;
+    assert_eq!(sample(&metrics, &format!("http_requests_total{{{}}}", route)), Some(2.0));
+    assert_eq!(sample(&metrics, &format!("http_request_duration_seconds_count{{{}}}", route)), Some(2.0));
+    assert_eq!(
+        sample(&metrics, r#"http_requests_total{method="GET",route="unmatched",status="404"}"#),
+        Some(1.0)
+    );
+    // The scrape itself is in flight
+    assert_eq!(sample(&metrics, "http_requests_in_flight"), Some(1.0));
+}
+
+#[tokio::test]
+async fn unknown_methods_share_one_label() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+    let client = reqwest::Client::new();
+
+    for method in ["FOO", "BAR"] {
+        let method = reqwest::Method::from_bytes(method.as_bytes()).unwrap();
+        client.request(method, format!("{}/healthz", app)).send().await.expect("request failed");
+    }
+    let metrics = scrape(&app).await;
+
+    assert!(!metrics.contains("FOO") && !metrics.contains("BAR"), "{}", metrics);
+    let other = metrics
+        .lines()
+        .filter(|line| line.starts_with(r#"http_requests_total{method="other",route="/healthz""#))
+        .map(|line| line.rsplit(' ').next().unwrap().parse::<f64>().unwrap())
+        .sum::<f64>();
+    assert_eq!(other, 2.0);
+}
+
+#[tokio::test]
+async fn elasticsearch_calls_and_cache_results_are_recorded() {
+    let es = MockEs::start().await;
+    es.respond("/categories/_search", 200, hits(vec![json!({ "name": "Tiên hiệp" })], 1));
+    es.respond("/stories/_search", 500, json!({ "error": "boom" }));
+    let mut config = test_config(&es);
+    config.elasticsearch.retry.max_retries = 0;
+    let app = spawn_app(config).await;
+
+    get(&format!("{}/categories/list", app)).await;
+    get(&format!("{}/categories/list", app)).await;
+    get(&format!("{}/stories/list", app)).await;
+    let metrics = scrape(&app).await;
+
+    assert_eq!(
+        sample(&metrics, r#"es_request_duration_seconds_count{index="categories",endpoint="_search",outcome="success"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&metrics, r#"es_request_duration_seconds_count{index="stories",endpoint="_search",outcome="server_error"}"#),
+        Some(1.0)
+    );
+    let lookups = r#"response_cache_lookups_total{route="/categories/list",result="#;
+    assert_eq!(sample(&metrics, &format!(r#"{}"hit"}}"#, lookups)), Some(1.0));
+    assert_eq!(sample(&metrics, &format!(r#"{}"miss"}}"#, lookups)), Some(1.0));
+    assert!(sample(&metrics, "response_cache_hit_ratio").unwrap() > 0.0);
+}
+
+#[tokio::test]
+async fn elasticsearch_calls_are_labelled_by_index_and_endpoint() {
+    let es = MockEs::start().await;
+    let empty = json!({ "responses": [hits(vec![], 0), hits(vec![], 0), hits(vec![], 0)] });
+    es.respond("/_msearch", 200, empty);
+    es.respond("/_cluster/health", 200, json!({ "status": "green" }));
+    let app = spawn_app(test_config(&es)).await;
+
+    get(&format!("{}/search/suggest?q=tien", app)).await;
+    get(&format!("{}/readyz", app)).await;
+    let metrics = scrape(&app).await;
+
+    assert_eq!(
+        sample(&metrics, r#"es_request_duration_seconds_count{index="stories,authors,categories",endpoint="_msearch",outcome="success"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&metrics, r#"es_request_duration_seconds_count{endpoint="_cluster/health",outcome="success"}"#),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn compression_savings_are_recorded() {
+    let es = MockEs::start().await;
+    let docs = (0..50).map(|i| json!({ "title": format!("Truyện số {}", i) })).collect();
+    es.respond("/stories/_search", 200, hits(docs, 50));
+    let app = spawn_app(test_config(&es)).await;
+
+    reqwest::Client::new()
+        .get(format!("{}/stories/list", app))
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    let metrics = scrape(&app).await;
+
+    let input = sample(&metrics, r#"compression_input_bytes_total{encoding="gzip"}"#).unwrap();
+    let output = sample(&metrics, r#"compression_output_bytes_total{encoding="gzip"}"#).unwrap();
+    assert!(input > 1024.0);
+    assert!(output > 0.0 && output < input);
+}
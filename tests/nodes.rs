@@ -0,0 +1,77 @@
+mod common;
+
+use comic_es::config::Config;
+use common::{get, spawn_app, test_config, MockEs};
+use serde_json::json;
+use std::time::Duration;
+
+// No response cache, so every request reaches a node
+fn cluster_config(urls: Vec<String>, es: &MockEs) -> Config {
+    let mut config = test_config(es);
+    config.cache.enabled = false;
+    config.elasticsearch.urls = urls;
+    config.elasticsearch.retry.backoff_ms = 10;
+    config.elasticsearch.retry.max_backoff_ms = 20;
+    config
+}
+
+#[tokio::test]
+async fn searches_are_spread_over_nodes() {
+    let first = MockEs::start().await;
+    let second = MockEs::start().await;
+    let app = spawn_app(cluster_config(vec![first.url.clone(), second.url.clone()], &first)).await;
+
+    for _ in 0..4 {
+        let (status, _) = get(&format!("{}/categories/list", app)).await;
+        assert_eq!(status, 200);
+    }
+
+    assert_eq!(first.search_count("/categories/_search"), 2);
+    assert_eq!(second.search_count("/categories/_search"), 2);
+}
+
+#[tokio::test]
+async fn unreachable_node_is_skipped() {
+    let es = MockEs::start().await;
+    let dead = "http://127.0.0.1:1".to_string();
+    let app = spawn_app(cluster_config(vec![dead, es.url.clone()], &es)).await;
+
+    for _ in 0..4 {
+        let (status, _) = get(&format!("{}/categories/list", app)).await;
+        assert_eq!(status, 200);
+    }
+
+    // Once marked down, the dead node no longer costs a retry
+    assert_eq!(es.search_count("/categories/_search"), 4);
+}
+
+#[tokio::test]
+async fn sniffed_nodes_join_the_pool() {
+    let seed = MockEs::start().await;
+    let sniffed = MockEs::start().await;
+    let address = sniffed.url.trim_start_matches("http://");
+    seed.respond(
+        "/_nodes/http",
+        200,
+        json!({ "nodes": { "a1": { "http": { "publish_address": format!("localhost/{}", address) } } } }),
+    );
+    let mut config = cluster_config(vec![seed.url.clone()], &seed);
+    config.elasticsearch.sniff = true;
+    let app = spawn_app(config).await;
+
+    for _ in 0..50 {
+        if seed.requests().iter().any(|req| req.path == "/_nodes/http") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // The pool is updated once the response is read
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    for _ in 0..4 {
+        let (status, _) = get(&format!("{}/categories/list", app)).await;
+        assert_eq!(status, 200);
+    }
+
+    assert_eq!(seed.search_count("/categories/_search"), 2);
+    assert_eq!(sniffed.search_count("/categories/_search"), 2);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{get, header, hits, spawn_app, test_config, MockEs};
+use comic_es::catch_panic::catch_panic;
+use hyper::{Body, Response};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn panicking_handler_becomes_an_internal_error() {
+    let response = catch_panic("abc123", async {
+        panic!("handler bug");
+        #[allow(unreachable_code)]
+        Response::new(Body::empty())
+    })
+    .await;
+
+    assert_eq!(response.status(), 500);
+    assert_eq!(response.headers()["x-request-id"], "abc123");
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["code"], "internal_error");
+    assert_eq!(body["data"]["request_id"], "abc123");
+}
+
+#[tokio::test]
+async fn completed_handler_passes_through() {
+    let response = catch_panic("abc123", async { Response::new(Body::from("ok")) }).await;
+
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("x-request-id").is_none());
+}
+
+#[tokio::test]
+async fn server_survives_a_panicking_request() {
+    let es = MockEs::start().await;
+    // Adding `_highlight` to a `_source` that is not an object panics
+    es.respond("/stories/_search", 200, hits(vec![json!(["not", "an", "object"])], 1));
+    let app = spawn_app(test_config(&es)).await;
+
+    let res = reqwest::Client::new()
+        .get(format!("{}/stories/list?title=tien&highlight=true", app))
+        .header("X-Request-Id", "panic-123")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), 500);
+    assert_eq!(header(&res, "x-request-id"), Some("panic-123"));
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["error"], true);
+    assert_eq!(body["code"], "internal_error");
+    assert_eq!(body["data"]["request_id"], "panic-123");
+
+    let (status, _) = get(&format!("{}/healthz", app)).await;
+    assert_eq!(status, 200);
+}
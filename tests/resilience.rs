@@ -0,0 +1,107 @@
+mod common;
+
+use comic_es::config::Config;
+use common::{get, hits, spawn_app, test_config, MockEs};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+// No response cache, so every request reaches the mock
+fn uncached_config(es: &MockEs) -> Config {
+    let mut config = test_config(es);
+    config.cache.enabled = false;
+    config.elasticsearch.retry.backoff_ms = 10;
+    config.elasticsearch.retry.max_backoff_ms = 20;
+    config
+}
+
+#[tokio::test]
+async fn overloaded_upstream_is_retried() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 503, json!({ "error": "unavailable_shards_exception" }));
+    let app = spawn_app(uncached_config(&es)).await;
+
+    let (status, _) = get(&format!("{}/stories/list", app)).await;
+
+    assert_eq!(status, 502);
+    assert_eq!(es.search_count("/stories/_search"), 3);
+}
+
+#[tokio::test]
+async fn query_errors_are_not_retried() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 400, json!({ "error": "parsing_exception" }));
+    let app = spawn_app(uncached_config(&es)).await;
+
+    get(&format!("{}/stories/list", app)).await;
+
+    assert_eq!(es.search_count("/stories/_search"), 1);
+}
+
+#[tokio::test]
+async fn slow_upstream_times_out() {
+    let es = MockEs::start().await;
+    es.delay(Duration::from_secs(5));
+    let mut config = uncached_config(&es);
+    config.elasticsearch.request_timeout_ms = 100;
+    config.elasticsearch.retry.max_retries = 0;
+    let app = spawn_app(config).await;
+
+    let started = Instant::now();
+    let (status, body) = get(&format!("{}/categories/list", app)).await;
+
+    assert_eq!(status, 503);
+    assert_eq!(body["code"], "upstream_unavailable");
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn breaker_fails_fast_and_recovers_after_a_probe() {
+    let es = MockEs::start().await;
+    es.respond("/categories/_search", 500, json!({ "error": "boom" }));
+    let mut config = uncached_config(&es);
+    config.elasticsearch.retry.max_retries = 0;
+    config.elasticsearch.circuit_breaker.failure_threshold = 2;
+    config.elasticsearch.circuit_breaker.open_ms = 200;
+    let app = spawn_app(config).await;
+    let url = format!("{}/categories/list", app);
+
+    assert_eq!(get(&url).await.0, 502);
+    assert_eq!(get(&url).await.0, 502);
+
+    // Open: answered without calling Elasticsearch
+    let (status, body) = get(&url).await;
+    assert_eq!(status, 503);
+    assert_eq!(body["code"], "upstream_unavailable");
+    assert_eq!(es.search_count("/categories/_search"), 2);
+
+    es.respond("/categories/_search", 200, hits(vec![json!({ "title": "Tiên Hiệp" })], 1));
+    tokio::time::sleep(Duration::from_millis(250)).await;
+
+    assert_eq!(get(&url).await.0, 200);
+    assert_eq!(get(&url).await.0, 200);
+    assert_eq!(es.search_count("/categories/_search"), 4);
+}
+
+#[tokio::test]
+async fn retried_calls_count_once_towards_the_breaker() {
+    let es = MockEs::start().await;
+    es.respond("/categories/_search", 503, json!({ "error": "unavailable_shards_exception" }));
+    let mut config = uncached_config(&es);
+    config.elasticsearch.retry.max_retries = 2;
+    config.elasticsearch.circuit_breaker.failure_threshold = 2;
+    let app = spawn_app(config).await;
+    let url = format!("{}/categories/list", app);
+
+    // Each call makes every attempt and reports the upstream error
+    for calls in 1..=2 {
+        let (status, body) = get(&url).await;
+        assert_eq!(status, 502);
+        assert_eq!(body["code"], "upstream_error");
+        assert_eq!(es.search_count("/categories/_search"), 3 * calls);
+    }
+
+    // Two failed calls opened it
+    let (status, _) = get(&url).await;
+    assert_eq!(status, 503);
+    assert_eq!(es.search_count("/categories/_search"), 6);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::{get, header, hits, spawn_app, test_config, MockEs};
+use serde_json::json;
+use std::time::Duration;
+
+async fn x_cache(url: &str) -> String {
+    let res = reqwest::get(url).await.expect("request failed");
+    res.headers()["x-cache"].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn repeated_requests_are_served_from_memory() {
+    let es = MockEs::start().await;
+    es.respond("/categories/_search", 200, hits(vec![json!({ "title": "Tiên Hiệp" })], 1));
+    let app = spawn_app(test_config(&es)).await;
+
+    assert_eq!(x_cache(&format!("{}/categories/list", app)).await, "MISS");
+    assert_eq!(x_cache(&format!("{}/categories/list", app)).await, "HIT");
+    let (_, body) = get(&format!("{}/categories/list", app)).await;
+
+    assert_eq!(body["data"]["list"][0]["title"], "Tiên Hiệp");
+    assert_eq!(es.search_count("/categories/_search"), 1);
+}
+
+#[tokio::test]
+async fn hits_carry_their_age() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+    let url = format!("{}/categories/list", app);
+
+    let miss = reqwest::get(&url).await.expect("request failed");
+    assert_eq!(header(&miss, "age"), None);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    let hit = reqwest::get(&url).await.expect("request failed");
+    assert_eq!(header(&hit, "x-cache"), Some("HIT"));
+    assert_eq!(header(&hit, "age"), Some("1"));
+}
+
+#[tokio::test]
+async fn keys_ignore_parameter_order_and_empty_values() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    get(&format!("{}/stories/list?page=2&size=5", app)).await;
+    get(&format!("{}/stories/list?size=5&title=&page=2", app)).await;
+    get(&format!("{}/stories/list?size=5&page=3", app)).await;
+
+    assert_eq!(es.search_count("/stories/_search"), 2);
+}
+
+#[tokio::test]
+async fn concurrent_misses_make_one_upstream_call() {
+    let es = MockEs::start().await;
+    es.delay(Duration::from_millis(200));
+    let app = spawn_app(test_config(&es)).await;
+
+    let url = format!("{}/chapters/list/1", app);
+    let requests = (0..10).map(|_| get(&url));
+    let responses = futures_util::future::join_all(requests).await;
+
+    assert!(responses.iter().all(|(status, _)| *status == 200));
+    assert_eq!(es.search_count("/chapters/_search"), 1);
+
+    let (_, stats) = get(&format!("{}/cache/stats", app)).await;
+    assert_eq!(stats["data"]["misses"], 10);
+    assert_eq!(stats["data"]["coalesced"], 9);
+    assert_eq!(stats["data"]["entries"], 1);
+}
+
+#[tokio::test]
+async fn errors_are_not_cached() {
+    let es = MockEs::start().await;
+    es.respond("/authors/_search", 500, json!({ "error": "boom" }));
+    let app = spawn_app(test_config(&es)).await;
+
+    let url = format!("{}/authors/detail_by_url_key/nhi-can", app);
+    assert_eq!(get(&url).await.0, 502);
+    es.respond("/authors/_search", 200, hits(vec![json!({ "title": "Nhĩ Căn" })], 1));
+
+    let (status, body) = get(&url).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["title"], "Nhĩ Căn");
+}
+
+#[tokio::test]
+async fn cache_can_be_disabled() {
+    let es = MockEs::start().await;
+    let mut config = test_config(&es);
+    config.cache.enabled = false;
+    let app = spawn_app(config).await;
+
+    get(&format!("{}/categories/list", app)).await;
+    get(&format!("{}/categories/list", app)).await;
+
+    assert_eq!(es.search_count("/categories/_search"), 2);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::{get, hits, spawn_app, test_config, MockEs};
+use serde_json::json;
+
+#[tokio::test]
+async fn unknown_route_is_not_found() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _) = get(&format!("{}/stories/list_by_category", app)).await;
+
+    assert_eq!(status, 404);
+    assert!(es.requests().is_empty());
+}
+
+#[tokio::test]
+async fn wrong_method_is_rejected_with_allow_header() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let res = reqwest::Client::new()
+        .post(format!("{}/stories/list", app))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status().as_u16(), 405);
+    assert_eq!(res.headers()["allow"], "GET");
+}
+
+#[tokio::test]
+async fn path_params_are_percent_decoded() {
+    let es = MockEs::start().await;
+    es.respond("/categories/_search", 200, hits(vec![json!({ "url_key": "tiên-hiệp" })], 1));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _) = get(&format!("{}/categories/detail_by_url_key/ti%C3%AAn-hi%E1%BB%87p", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.last_body("/categories/_search")["query"], json!({ "term": { "url_key": "tiên-hiệp" } }));
+}
+
+#[tokio::test]
+async fn categories_list_filters_on_type() {
+    let es = MockEs::start().await;
+    es.respond("/categories/_search", 200, hits(vec![json!({ "title": "Tiên Hiệp" })], 1));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/categories/list?type_category=genre", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.last_body("/categories/_search"), json!({
+        "query": { "bool": { "must": [{ "term": { "type_category": "genre" } }] } },
+        "size": 1000
+    }));
+    assert_eq!(body["data"]["list"], json!([{ "title": "Tiên Hiệp" }]));
+}
+
+#[tokio::test]
+async fn author_detail_queries_authors_index() {
+    let es = MockEs::start().await;
+    es.respond("/authors/_search", 200, hits(vec![json!({ "url_key": "nhi-can" })], 1));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/authors/detail_by_url_key/nhi-can", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({ "url_key": "nhi-can" }));
+}
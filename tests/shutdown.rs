@@ -0,0 +1,98 @@
+mod common;
+
+use comic_es::config::Config;
+use common::{get, test_config, MockEs};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+struct App {
+    url: String,
+    shutdown: oneshot::Sender<()>,
+    server: JoinHandle<Result<(), hyper::Error>>,
+}
+
+async fn spawn_app(config: Config) -> App {
+    let (shutdown, signal) = oneshot::channel::<()>();
+    let (addr, server) = comic_es::serve_with_shutdown(config, async move {
+        let _ = signal.await;
+    })
+    .expect("failed to start service");
+
+    App { url: format!("http://{}", addr), shutdown, server: tokio::spawn(server) }
+}
+
+// The response cache would answer without reaching the slow mock
+fn slow_config(es: &MockEs) -> Config {
+    es.delay(Duration::from_millis(500));
+    let mut config = test_config(es);
+    config.cache.enabled = false;
+    config.server.shutdown_delay_ms = 0;
+    config
+}
+
+#[tokio::test]
+async fn in_flight_requests_finish_before_exit() {
+    let es = MockEs::start().await;
+    let app = spawn_app(slow_config(&es)).await;
+
+    let url = format!("{}/categories/list", app.url);
+    let in_flight = tokio::spawn(async move { get(&url).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    app.shutdown.send(()).unwrap();
+
+    let (status, _) = in_flight.await.unwrap();
+    assert_eq!(status, 200);
+    tokio::time::timeout(Duration::from_secs(2), app.server).await.unwrap().unwrap().unwrap();
+    assert!(reqwest::get(format!("{}/healthz", app.url)).await.is_err());
+}
+
+#[tokio::test]
+async fn readiness_fails_while_shutting_down() {
+    let es = MockEs::start().await;
+    let mut config = test_config(&es);
+    // Far longer than the test, so the listener stays open throughout
+    config.server.shutdown_delay_ms = 60_000;
+    let app = spawn_app(config).await;
+    let client = reqwest::Client::new();
+
+    app.shutdown.send(()).unwrap();
+    // The readiness flip is observed, not waited for
+    let readyz = format!("{}/readyz", app.url);
+    let started = Instant::now();
+    let body = loop {
+        let res = client.get(&readyz).send().await.expect("request failed");
+        if res.status() == 503 {
+            break res.json::<serde_json::Value>().await.unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_secs(5), "readiness never failed");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    assert_eq!(body["data"]["status"], "shutting_down");
+
+    // Still serving during the delay
+    let res = client.get(format!("{}/healthz", app.url)).send().await.expect("request failed");
+    assert_eq!(res.status(), 200);
+    assert!(!app.server.is_finished());
+    app.server.abort();
+}
+
+#[tokio::test]
+async fn draining_stops_at_the_deadline() {
+    let es = MockEs::start().await;
+    es.delay(Duration::from_secs(10));
+    let mut config = test_config(&es);
+    config.elasticsearch.request_timeout_ms = 20_000;
+    config.server.shutdown_delay_ms = 0;
+    config.server.shutdown_timeout_ms = 200;
+    let app = spawn_app(config).await;
+
+    let url = format!("{}/categories/list", app.url);
+    tokio::spawn(async move { reqwest::get(url).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let started = Instant::now();
+    app.shutdown.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(2), app.server).await.unwrap().unwrap().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::{header, hits, spawn_app, test_config, MockEs};
+use comic_es::config::Config;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+// Entries expire at once, so every request goes upstream
+fn expiring_config(es: &MockEs) -> Config {
+    let mut config = test_config(es);
+    config.cache.max_ttl_secs = 0;
+    config
+}
+
+#[tokio::test]
+async fn last_good_response_is_served_when_upstream_fails() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 200, hits(vec![json!({ "story_id": "1", "title": "Tiên Nghịch" })], 1));
+    let app = spawn_app(expiring_config(&es)).await;
+    let url = format!("{}/stories/detail_by_url_key/tien-nghich", app);
+
+    let fresh = reqwest::get(&url).await.unwrap();
+    assert_eq!(header(&fresh, "x-cache"), Some("MISS"));
+
+    es.respond("/stories/_search", 500, json!({ "error": "cluster down" }));
+    let res = reqwest::get(&url).await.unwrap();
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(header(&res, "x-cache"), Some("STALE"));
+    assert_eq!(header(&res, "warning"), Some("110 - \"Response is Stale\""));
+    assert_eq!(header(&res, "cache-control"), Some("no-store"));
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["title"], "Tiên Nghịch");
+}
+
+#[tokio::test]
+async fn expired_entries_are_reported_apart_from_fresh_ones() {
+    let es = MockEs::start().await;
+    let app = spawn_app(expiring_config(&es)).await;
+
+    reqwest::get(&format!("{}/categories/list", app)).await.unwrap();
+    let stats: Value = reqwest::get(&format!("{}/cache/stats", app)).await.unwrap().json().await.unwrap();
+
+    assert_eq!(stats["data"]["entries"], 0);
+    assert_eq!(stats["data"]["stale_entries"], 1);
+}
+
+#[tokio::test]
+async fn failures_without_a_good_response_are_reported() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 500, json!({ "error": "cluster down" }));
+    let app = spawn_app(expiring_config(&es)).await;
+
+    let res = reqwest::get(&format!("{}/stories/detail_by_url_key/tien-nghich", app)).await.unwrap();
+
+    assert_eq!(res.status(), 502);
+    assert_eq!(header(&res, "x-cache"), Some("MISS"));
+}
+
+#[tokio::test]
+async fn stale_if_error_can_be_disabled() {
+    let es = MockEs::start().await;
+    let mut config = expiring_config(&es);
+    config.cache.stale_if_error = false;
+    let app = spawn_app(config).await;
+    let url = format!("{}/categories/list", app);
+
+    reqwest::get(&url).await.unwrap();
+    es.respond("/categories/_search", 500, json!({ "error": "cluster down" }));
+
+    assert_eq!(reqwest::get(&url).await.unwrap().status(), 502);
+}
+
+#[tokio::test]
+async fn disk_store_survives_a_restart() {
+    let dir = std::env::temp_dir().join(format!("comic-es-stale-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let es = MockEs::start().await;
+    es.respond("/categories/_search", 200, hits(vec![json!({ "title": "Huyền Huyễn" })], 1));
+    let mut config = expiring_config(&es);
+    config.cache.stale_dir = Some(dir.clone());
+    let first = spawn_app(config.clone()).await;
+    reqwest::get(&format!("{}/categories/list", first)).await.unwrap();
+
+    // The write happens in the background
+    for _ in 0..50 {
+        if std::fs::read_dir(&dir).map(|entries| entries.count() > 0).unwrap_or(false) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    es.respond("/categories/_search", 500, json!({ "error": "cluster down" }));
+    let second = spawn_app(config).await;
+    let res = reqwest::get(&format!("{}/categories/list", second)).await.unwrap();
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(header(&res, "x-cache"), Some("STALE"));
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["data"]["list"][0]["title"], "Huyền Huyễn");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn stored_files(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json")).count())
+        .unwrap_or(0)
+}
+
+// The writes happen in the background
+async fn wait_for_files(dir: &std::path::Path, files: usize) {
+    for _ in 0..50 {
+        if stored_files(dir) >= files {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn disk_store_is_capped() {
+    let dir = std::env::temp_dir().join(format!("comic-es-stale-cap-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let es = MockEs::start().await;
+    let mut config = expiring_config(&es);
+    config.cache.stale_dir = Some(dir.clone());
+    config.cache.stale_max_files = 2;
+    let app = spawn_app(config).await;
+
+    for (written, title) in ["a", "b", "c", "d"].into_iter().enumerate() {
+        reqwest::get(&format!("{}/stories/list?title={}", app, title)).await.unwrap();
+        wait_for_files(&dir, (written + 1).min(2)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(stored_files(&dir), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn expired_files_are_deleted() {
+    let dir = std::env::temp_dir().join(format!("comic-es-stale-expired-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let es = MockEs::start().await;
+    let mut config = expiring_config(&es);
+    config.cache.stale_dir = Some(dir.clone());
+    config.cache.stale_max_age_secs = 0;
+    let app = spawn_app(config).await;
+    let url = format!("{}/categories/list", app);
+
+    reqwest::get(&url).await.unwrap();
+    wait_for_files(&dir, 1).await;
+    assert_eq!(stored_files(&dir), 1);
+
+    // Too old to be served, so the failure is reported and the file goes
+    es.respond("/categories/_search", 500, json!({ "error": "cluster down" }));
+    assert_eq!(reqwest::get(&url).await.unwrap().status(), 502);
+    assert_eq!(stored_files(&dir), 0);
+    let _ = std::fs::remove_dir_all(&dir);
+}
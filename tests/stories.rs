@@ -0,0 +1,230 @@
+mod common;
+
+use common::{get, hits, spawn_app, test_config, MockEs};
+use serde_json::json;
+
+#[tokio::test]
+async fn list_builds_must_clauses_from_filters() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 200, hits(vec![json!({ "title": "Tiên Nghịch" })], 21));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!(
+        "{}/stories/list?title=ti%C3%AAn+ngh%E1%BB%8Bch&author_id=7&is_full=true&sort_by_latest=true&page=3&size=5",
+        app
+    )).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.last_body("/stories/_search"), json!({
+        "query": {
+            "bool": {
+                "must": [
+                    {
+                        "multi_match": {
+                            "query": "tiên nghịch",
+                            "fields": [
+                                "title^5",
+                                "title.folded^4",
+                                "alternate_titles^4",
+                                "alternate_titles.folded^3",
+                                "author.title^2",
+                                "author.title.folded^1.5",
+                                "description",
+                                "description.folded^0.5"
+                            ],
+                            "type": "best_fields",
+                            "tie_breaker": 0.3
+                        }
+                    },
+                    { "term": { "author.author_id": "7" } },
+                    { "term": { "is_full": true } }
+                ],
+                "should": [
+                    { "match_phrase": { "title": { "query": "tiên nghịch", "boost": 10 } } },
+                    { "match_phrase": { "title.folded": { "query": "tiên nghịch", "boost": 6 } } }
+                ]
+            }
+        },
+        "from": 10,
+        "size": 5,
+        "sort": [{ "updated_date": { "order": "desc" } }]
+    }));
+    // Plenty of results, so no corrections are looked for
+    assert_eq!(es.search_count("/stories/_search"), 1);
+    assert_eq!(body, json!({
+        "message": "Successfully",
+        "error": false,
+        "data": {
+            "list": [{ "title": "Tiên Nghịch" }],
+            "total": 21,
+            "total_page": 5
+        }
+    }));
+}
+
+#[tokio::test]
+async fn sparse_list_suggests_corrections() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 200, json!({
+        "hits": { "total": { "value": 0 }, "hits": [] },
+        "suggest": {
+            "title": [{ "text": "tien nghic", "offset": 0, "length": 10, "options": [
+                { "text": "tiên nghịch", "score": 0.12, "collate_match": true },
+                { "text": "tiên nghịch 2", "score": 0.03, "collate_match": true }
+            ] }],
+            "author": [{ "text": "tien nghic", "offset": 0, "length": 10, "options": [
+                { "text": "tiên nghịch", "score": 0.01, "collate_match": true },
+                { "text": "thiên tằm", "score": 0.05, "collate_match": true }
+            ] }]
+        }
+    }));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/stories/list?title=tien+nghic", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.last_body("/stories/_search"), json!({
+        "size": 0,
+        "suggest": {
+            "text": "tien nghic",
+            "title": did_you_mean("title"),
+            "author": did_you_mean("author.title")
+        }
+    }));
+    assert_eq!(body["data"]["total"], 0);
+    assert_eq!(body["data"]["suggestions"], json!(["tiên nghịch", "thiên tằm", "tiên nghịch 2"]));
+}
+
+#[tokio::test]
+async fn failed_corrections_leave_the_list_intact() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 200, hits(vec![json!({ "title": "Tiên Nghịch" })], 1));
+    es.respond_when("/stories/_search", "\"suggest\"", 400, json!({ "error": "no such field [author.title]" }));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/stories/list?title=tien+nghic", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.search_count("/stories/_search"), 2);
+    assert_eq!(body["data"]["list"], json!([{ "title": "Tiên Nghịch" }]));
+    assert!(body["data"].get("suggestions").is_none());
+}
+
+#[tokio::test]
+async fn list_highlights_matches_on_request() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 200, json!({
+        "hits": { "total": { "value": 1 }, "hits": [{
+            "_id": "1",
+            "_source": { "title": "Tiên Nghịch", "description": "Vương Lâm bước lên con đường tu tiên." },
+            "highlight": {
+                "title.folded": ["<mark>Tiên</mark> Nghịch"],
+                "description": ["Vương Lâm bước lên con đường tu <mark>tiên</mark>."],
+                "description.folded": ["Vương Lâm bước lên con đường tu <mark>tiên</mark>."]
+            }
+        }] }
+    }));
+    let mut config = test_config(&es);
+    config.search.highlight_pre_tag = "<mark>".to_string();
+    config.search.highlight_post_tag = "</mark>".to_string();
+    config.search.highlight_fragment_size = 80;
+    let app = spawn_app(config).await;
+
+    let (status, body) = get(&format!("{}/stories/list?title=tien&highlight=true", app)).await;
+
+    assert_eq!(status, 200);
+    // The search itself, not the suggestions asked for after it
+    assert_eq!(es.requests()[0].body["highlight"], json!({
+        "encoder": "html",
+        "pre_tags": ["<mark>"],
+        "post_tags": ["</mark>"],
+        "fragment_size": 80,
+        "fields": {
+            "title": { "number_of_fragments": 0 },
+            "title.folded": { "number_of_fragments": 0 },
+            "description": {},
+            "description.folded": {}
+        }
+    }));
+    assert_eq!(body["data"]["list"][0]["_highlight"], json!({
+        "title": ["<mark>Tiên</mark> Nghịch"],
+        "description": ["Vương Lâm bước lên con đường tu <mark>tiên</mark>."]
+    }));
+}
+
+#[tokio::test]
+async fn list_without_filters_uses_defaults() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/stories/list", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.last_body("/stories/_search"), json!({
+        "query": { "bool": { "must": [] } },
+        "from": 0,
+        "size": 10
+    }));
+    assert_eq!(body["data"]["total"], 0);
+    assert_eq!(body["data"]["list"], json!([]));
+}
+
+#[tokio::test]
+async fn list_by_category_filters_on_category_id() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _) = get(&format!("{}/stories/list_by_category/42?page=2&size=20", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(es.last_body("/stories/_search"), json!({
+        "query": {
+            "bool": {
+                "must": [{ "term": { "categories.category_id.keyword": "42" } }]
+            }
+        },
+        "from": 20,
+        "size": 20
+    }));
+}
+
+#[tokio::test]
+async fn detail_returns_the_story_source() {
+    let es = MockEs::start().await;
+    es.respond("/stories/_search", 200, hits(vec![json!({ "url_key": "tien-nghich" })], 1));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/stories/detail_by_url_key/tien-nghich", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({ "url_key": "tien-nghich" }));
+    assert_eq!(es.last_body("/stories/_search"), json!({
+        "query": { "term": { "url_key": "tien-nghich" } },
+        "size": 1
+    }));
+}
+
+#[tokio::test]
+async fn detail_of_unknown_story_is_not_found() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _) = get(&format!("{}/stories/detail_by_url_key/missing", app)).await;
+
+    assert_eq!(status, 404);
+}
+
+fn did_you_mean(field: &str) -> serde_json::Value {
+    json!({
+        "phrase": {
+            "field": field,
+            "size": 3,
+            "max_errors": 2,
+            "direct_generator": [{ "field": field, "suggest_mode": "missing" }],
+            "collate": {
+                "query": { "source": { "match": { field: { "query": "{{suggestion}}", "operator": "and" } } } },
+                "prune": false
+            }
+        }
+    })
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::{fixtures_config, get, hits, spawn_app, test_config, MockEs};
+use serde_json::{json, Value};
+
+fn url_keys(list: &Value) -> Vec<&str> {
+    list.as_array().unwrap().iter().map(|item| item["url_key"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn suggestions_come_from_one_msearch() {
+    let es = MockEs::start().await;
+    es.respond("/_msearch", 200, json!({
+        "responses": [
+            hits(vec![json!({ "title": "Tiên Nghịch", "url_key": "tien-nghich" })], 1),
+            hits(vec![], 0),
+            hits(vec![json!({ "title": "Tiên Hiệp", "url_key": "tien-hiep" })], 1)
+        ]
+    }));
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/search/suggest?q=tien+ng&size=3", app)).await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["data"], json!({
+        "stories": [{ "title": "Tiên Nghịch", "url_key": "tien-nghich" }],
+        "authors": [],
+        "categories": [{ "title": "Tiên Hiệp", "url_key": "tien-hiep" }]
+    }));
+
+    let lines: Vec<Value> = es.requests()[0].raw_body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let indices: Vec<&Value> = lines.iter().step_by(2).map(|header| &header["index"]).collect();
+    assert_eq!(indices, vec!["stories", "authors", "categories"]);
+    assert_eq!(lines[1], json!({
+        "query": {
+            "multi_match": {
+                "query": "tien ng",
+                "type": "bool_prefix",
+                "fields": ["title.suggest", "title.suggest._2gram", "title.suggest._3gram"]
+            }
+        },
+        "size": 3,
+        "_source": ["title", "url_key"]
+    }));
+}
+
+#[tokio::test]
+async fn query_is_required_and_size_is_capped() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/search/suggest?q=+", app)).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["data"]["parameter"], "q");
+
+    let (status, body) = get(&format!("{}/search/suggest?q=tien&size=500", app)).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["data"]["parameter"], "size");
+    assert!(es.requests().is_empty());
+}
+
+#[tokio::test]
+async fn completes_titles_without_diacritics() {
+    let app = spawn_app(fixtures_config()).await;
+
+    let (_, body) = get(&format!("{}/search/suggest?q=tien", app)).await;
+    assert_eq!(url_keys(&body["data"]["stories"]), vec!["tien-nghich"]);
+    assert_eq!(url_keys(&body["data"]["categories"]), vec!["tien-hiep"]);
+    assert_eq!(url_keys(&body["data"]["authors"]), Vec::<&str>::new());
+
+    let (_, body) = get(&format!("{}/search/suggest?q=dau+ph", app)).await;
+    assert_eq!(url_keys(&body["data"]["stories"]), vec!["dau-pha-thuong-khung"]);
+
+    let (_, body) = get(&format!("{}/search/suggest?q=Thi%C3%AAn+t", app)).await;
+    assert_eq!(url_keys(&body["data"]["authors"]), vec!["thien-tam-tho-dau"]);
+}
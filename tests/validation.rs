@@ -0,0 +1,75 @@
+mod common;
+
+use common::{get, spawn_app, test_config, MockEs};
+
+#[tokio::test]
+async fn page_zero_is_rejected() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/stories/list?page=0", app)).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], true);
+    assert_eq!(body["data"]["parameter"], "page");
+    assert!(es.requests().is_empty());
+}
+
+#[tokio::test]
+async fn size_above_limit_is_rejected() {
+    let es = MockEs::start().await;
+    let mut config = test_config(&es);
+    config.pagination.max_page_size = 20;
+    let app = spawn_app(config).await;
+
+    let (status, body) = get(&format!("{}/chapters/list/1?size=100000", app)).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["data"]["parameter"], "size");
+}
+
+#[tokio::test]
+async fn result_window_is_enforced() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/stories/list_by_category/1?page=2000&size=10", app)).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["data"]["parameter"], "page");
+}
+
+#[tokio::test]
+async fn malformed_flag_is_rejected() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/stories/list?is_full=yes", app)).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["data"]["parameter"], "is_full");
+}
+
+#[tokio::test]
+async fn numeric_flags_are_accepted() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, _) = get(&format!("{}/stories/list?is_full=1&sort_by_latest=0", app)).await;
+
+    assert_eq!(status, 200);
+    let query = es.last_body("/stories/_search");
+    assert_eq!(query["query"]["bool"]["must"][0]["term"]["is_full"], true);
+    assert!(query.get("sort").is_none());
+}
+
+#[tokio::test]
+async fn overlong_url_key_is_rejected() {
+    let es = MockEs::start().await;
+    let app = spawn_app(test_config(&es)).await;
+
+    let (status, body) = get(&format!("{}/authors/detail_by_url_key/{}", app, "a".repeat(500))).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["data"]["parameter"], "url_key");
+}